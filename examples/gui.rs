@@ -1,24 +1,12 @@
-use barg::{FontGroup, Gui, Image, Size};
-
-use png::HasParameters;
-use std::fs::File;
-use std::io;
-
-/// Write the raster to a PNG (portable network graphics) file.
-pub fn write_png(
-    width: u32,
-    height: u32,
-    pixels: &[u8],
-    filename: &str,
-) -> io::Result<()> {
-    let fl = File::create(filename)?;
-    let ref mut bw = io::BufWriter::new(fl);
-    let mut enc = png::Encoder::new(bw, width, height);
-    enc.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
-    let mut writer = enc.write_header()?;
-    writer.write_image_data(&pixels[..])?;
-    Ok(())
-}
+use barg::{
+    Align, Column, FontGroup, Gui, Image, Line, Move, PathOp, Region, Row, Side,
+    Size, TextField, Width,
+};
+
+// A triangle icon, relative to the top left of it's column.
+const TRIANGLE: [PathOp; 3] =
+    [Move(0.0, 27.0), Line(13.5, 9.0), Line(27.0, 27.0)];
+const ICON: [([u8; 4], &[PathOp]); 1] = [([0, 0, 0, 255], &TRIANGLE)];
 
 fn main() {
     // Load GUI resources.
@@ -31,21 +19,44 @@ fn main() {
     let mut buffer = vec![0; w * h * 4];
     let mut surface = Image::new(Size(w as u16, h as u16));
 
-    gui.head(&mut surface, &mut buffer, &|row, color| {
-        *color = [48, 48, 64, 255, 1];
-        &[(&[], "Hello, worldy!"), (&[], "Test")]
-    });
+    // A focused text field, with "world" selected.
+    let mut name = TextField::new("Hello, world");
+    name.focus(true);
+    name.select(7..12);
+
+    gui.set_sidebar(160, Side::Left);
+    gui.set_columns(
+        Region::Page,
+        &[Column::new(Width::Fraction(0.5), Align::Left)],
+    );
+    gui.page(&mut surface, &mut buffer, &|row| {
+        let widgets = Row::new([0x80, 0xFF, 0x80, 255]).separator();
+        match row {
+            0 => Some(widgets.text("Hello, worldy!")),
+            1 => Some(widgets.text("Yo!").icon(&ICON).text("Sup‽")),
+            2 => Some(widgets.text("Notifications").toggle(true)),
+            3 => Some(widgets.text("Dark mode").toggle(false)),
+            4 => Some(widgets.text("Account").button("Sign out")),
+            5 => Some(widgets.text("Name").text_field(&name)),
+            6..=99 => Some(widgets.text("More rows…")),
+            _ => None,
+        }
+    }).unwrap();
 
-    gui.page(&mut surface, &mut buffer, &|row, color| {
-        *color = [0x80, 0xFF, 0x80, 255, 1];
+    gui.sidebar(&mut surface, &mut buffer, &|row| {
+        let widgets = Row::default();
         match row {
-            0 => &[(&[], "Hello, worldy!")],
-            1 => &[(&[], "Yo!"), (&[], "Sup‽")],
-            _ => &[],
+            0 => Some(widgets.text("Inbox")),
+            1 => Some(widgets.text("Sent")),
+            _ => None,
         }
-    });
+    }).unwrap();
+
+    gui.head(&mut surface, &mut buffer, &|_row| {
+        let widgets = Row::new([48, 48, 64, 255]).separator();
+        Some(widgets.text("Hello, worldy!").button("Test"))
+    }).unwrap();
 
     // Save the image to a PNG file.
-    write_png(w as u32, h as u32, buffer.as_slice(), "image_example.png")
-        .unwrap();
+    surface.save_png(&buffer, "image_example.png").unwrap();
 }
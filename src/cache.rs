@@ -0,0 +1,356 @@
+//! Caching rasterized glyphs for drawing text on an `Image`.
+
+use crate::text::{measure, Bounds};
+use crate::{footile, transform, FontGroup, PathOp};
+use std::collections::HashMap;
+use std::mem;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A cache of rasterized glyphs (coverage masks), so text that's drawn again
+/// is composited instead of rendered from paths.  Set it on an `Image` with
+/// `Image::set_glyph_cache()`.
+///
+/// Glyphs are kept for each font, grapheme and size, and for where they fall
+/// within a pixel (in quarter pixels).  When the cache uses more memory than
+/// its budget, the glyphs used least recently are dropped.  Fonts are told
+/// apart by the id set with `Image::set_font_id()`, so give each font its
+/// own id (or `clear()` the cache when replacing the font).
+///
+/// ```
+/// use barg::{FontGroup, GlyphCache, Image, Size};
+///
+/// let font = FontGroup::default();
+/// let mut image = Image::new(Size(200, 40));
+/// let mut buffer = vec![0; 200 * 40 * 4];
+///
+/// // Up to 1 MiB of glyphs.
+/// image.set_glyph_cache(Some(GlyphCache::new(1 << 20)));
+/// for _ in 0..2 {
+///     let black = [0, 0, 0, 255];
+///     image.text(black, (0.0, 0.0, 16.0), &font, "Hi", &mut buffer).unwrap();
+/// }
+/// assert!(image.glyph_cache().unwrap().memory() > 0);
+/// ```
+pub struct GlyphCache {
+    // Most bytes of memory to use.
+    budget: usize,
+    // Bytes of memory used.
+    memory: usize,
+    // Counts uses, to find the glyphs (and advances) used least recently.
+    tick: u64,
+    // Glyphs by grapheme, looked up by `&str` so cache hits don't allocate.
+    glyphs: HashMap<GlyphKey, HashMap<String, Glyph>>,
+    // How far the pen moves for a grapheme after another (by previous, then
+    // grapheme), with when it was last used.
+    advances: HashMap<AdvanceKey, Advances>,
+}
+
+impl GlyphCache {
+    /// Create an empty glyph cache, that uses up to `budget` bytes.
+    pub fn new(budget: usize) -> Self {
+        GlyphCache {
+            budget,
+            memory: 0,
+            tick: 0,
+            glyphs: HashMap::new(),
+            advances: HashMap::new(),
+        }
+    }
+
+    /// Get how many bytes of memory the cache uses (approximately).
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Drop every glyph.
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.advances.clear();
+        self.memory = 0;
+    }
+
+    // Rasterize `text` with `font` (cached as `font_id`) at `size` with the
+    // pen at `xy` (in pixels).  Returns the coverage, its area (x, y, width,
+    // height) in pixels, and where the pen ends up.
+    pub(crate) fn text(
+        &mut self,
+        font: &FontGroup,
+        font_id: u32,
+        size: f32,
+        xy: (f32, f32),
+        text: &str,
+    ) -> (Vec<u8>, (i32, i32, usize, usize), f32) {
+        let size_bits = size.to_bits();
+        let (y, offset_y) = quarters(xy.1);
+
+        // Place each grapheme, rasterizing it if it's not cached.
+        let mut placed = vec![];
+        let mut pen = xy.0;
+        let mut previous = "";
+        for grapheme in text.graphemes(true) {
+            let (x, offset_x) = quarters(pen);
+            let key = GlyphKey {
+                font: font_id,
+                size: size_bits,
+                offset: (offset_x, offset_y),
+            };
+            self.tick += 1;
+            let tick = self.tick;
+            let memory = &mut self.memory;
+            let glyphs = self.glyphs.entry(key).or_default();
+            let glyph = entry(glyphs, grapheme, || {
+                let glyph = rasterize(font, size, grapheme, key.offset);
+                *memory += glyph.cost(grapheme);
+                glyph
+            });
+            glyph.used = tick;
+            placed.push((x + glyph.x, y + glyph.y, key, grapheme));
+
+            pen += self.advance(font, font_id, size, previous, grapheme);
+            previous = grapheme;
+        }
+
+        // Combine the glyphs in one mask.
+        let mut glyphs = vec![];
+        for (x, y, key, grapheme) in placed {
+            let glyph = &self.glyphs[&key][grapheme];
+            if glyph.w > 0 {
+                glyphs.push((x, y, glyph));
+            }
+        }
+        let x0 = glyphs.iter().map(|g| g.0).min().unwrap_or(0);
+        let y0 = glyphs.iter().map(|g| g.1).min().unwrap_or(0);
+        let x1 = glyphs.iter().map(|g| g.0 + g.2.w as i32).max().unwrap_or(0);
+        let y1 = glyphs.iter().map(|g| g.1 + g.2.h as i32).max().unwrap_or(0);
+        let (w, h) = ((x1 - x0) as usize, (y1 - y0) as usize);
+        let mut coverage = vec![0; w * h];
+        for (x, y, glyph) in glyphs {
+            let (dx, dy) = ((x - x0) as usize, (y - y0) as usize);
+            for (row, line) in glyph.mask.chunks_exact(glyph.w).enumerate() {
+                let start = (dy + row) * w + dx;
+                let area = &mut coverage[start..start + glyph.w];
+                for (c, m) in area.iter_mut().zip(line) {
+                    *c = (*c).max(*m);
+                }
+            }
+        }
+
+        self.trim();
+        (coverage, (x0, y0, w, h), pen)
+    }
+
+    // Get how far the pen moves for `grapheme` after `previous`, including
+    // kerning between them.
+    fn advance(
+        &mut self,
+        font: &FontGroup,
+        font_id: u32,
+        size: f32,
+        previous: &str,
+        grapheme: &str,
+    ) -> f32 {
+        self.tick += 1;
+        let tick = self.tick;
+        let key = AdvanceKey {
+            font: font_id,
+            size: size.to_bits(),
+        };
+        let memory = &mut self.memory;
+        let advances = self.advances.entry(key).or_default();
+        let advances = entry(advances, previous, HashMap::new);
+        let advance = entry(advances, grapheme, || {
+            let pair = format!("{}{}", previous, grapheme);
+            let pair = measure(font, size, &pair).advance;
+            *memory += advance_cost(previous, grapheme);
+            (pair - measure(font, size, previous).advance, 0)
+        });
+        advance.1 = tick;
+        advance.0
+    }
+
+    // Drop the glyphs (and advances) used least recently, until the cache
+    // is well within its budget (so it's not trimmed every time).
+    fn trim(&mut self) {
+        if self.memory <= self.budget {
+            return;
+        }
+        let glyphs = self.glyphs.values().flatten();
+        let glyphs = glyphs.map(|(grapheme, g)| (g.used, g.cost(grapheme)));
+        let advances = self.advances.values().flatten().flat_map(|(p, a)| {
+            a.iter().map(move |(g, a)| (a.1, advance_cost(p, g)))
+        });
+        let mut uses: Vec<(u64, usize)> = glyphs.chain(advances).collect();
+        uses.sort_unstable();
+
+        let target = self.budget - self.budget / 4;
+        let mut oldest = 0;
+        for (used, cost) in uses {
+            if self.memory <= target {
+                break;
+            }
+            self.memory -= cost;
+            oldest = used;
+        }
+        self.glyphs.retain(|_, glyphs| {
+            glyphs.retain(|_, glyph| glyph.used > oldest);
+            !glyphs.is_empty()
+        });
+        self.advances.retain(|_, advances| {
+            advances.retain(|_, advances| {
+                advances.retain(|_, advance| advance.1 > oldest);
+                !advances.is_empty()
+            });
+            !advances.is_empty()
+        });
+    }
+}
+
+// What a glyph is rasterized for (besides the grapheme).
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    // Id of the font.
+    font: u32,
+    // Bits of the font size (f32).
+    size: u32,
+    // Where the pen is within the pixel, in quarter pixels.
+    offset: (u8, u8),
+}
+
+// A rasterized glyph.
+struct Glyph {
+    // Coverage (0 to 255) of each pixel.
+    mask: Vec<u8>,
+    // Top left of the mask, from the pen's pixel.
+    x: i32,
+    y: i32,
+    // Size of the mask.
+    w: usize,
+    h: usize,
+    // When it was last used.
+    used: u64,
+}
+
+impl Glyph {
+    // Get about how many bytes the glyph uses.
+    fn cost(&self, grapheme: &str) -> usize {
+        self.mask.len()
+            + grapheme.len()
+            + mem::size_of::<String>()
+            + mem::size_of::<Glyph>()
+    }
+}
+
+// What advances are measured for (besides the graphemes).
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+struct AdvanceKey {
+    // Id of the font.
+    font: u32,
+    // Bits of the font size (f32).
+    size: u32,
+}
+
+// Advances (with when they were last used) by previous grapheme, then by
+// grapheme.
+type Advances = HashMap<String, HashMap<String, (f32, u64)>>;
+
+// Get about how many bytes an advance for `grapheme` after `previous` uses.
+fn advance_cost(previous: &str, grapheme: &str) -> usize {
+    previous.len()
+        + grapheme.len()
+        + mem::size_of::<String>() * 2
+        + mem::size_of::<(f32, u64)>()
+}
+
+// Get the value for `key` in `map`, inserting `new()` if it's not there.
+// Unlike `HashMap::entry()`, the key is only copied into a `String` when
+// it's inserted.
+fn entry<'a, V>(
+    map: &'a mut HashMap<String, V>,
+    key: &str,
+    new: impl FnOnce() -> V,
+) -> &'a mut V {
+    if !map.contains_key(key) {
+        map.insert(key.to_string(), new());
+    }
+    map.get_mut(key).unwrap()
+}
+
+// Split a coordinate into a whole pixel and quarter pixels (0 to 3).
+fn quarters(v: f32) -> (i32, u8) {
+    let quarters = (v * 4.0).round() as i32;
+    (quarters.div_euclid(4), quarters.rem_euclid(4) as u8)
+}
+
+// Copy a path operation (`PathOp` isn't `Clone`, so clippy's suggested `*op`
+// doesn't compile).
+#[allow(clippy::needless_match)]
+fn copy(op: &PathOp) -> PathOp {
+    match *op {
+        PathOp::Move(x, y) => PathOp::Move(x, y),
+        PathOp::Line(x, y) => PathOp::Line(x, y),
+        PathOp::Quad(bx, by, x, y) => PathOp::Quad(bx, by, x, y),
+        PathOp::Cubic(bx, by, cx, cy, x, y) => {
+            PathOp::Cubic(bx, by, cx, cy, x, y)
+        }
+        PathOp::Close() => PathOp::Close(),
+        PathOp::PenWidth(w) => PathOp::PenWidth(w),
+    }
+}
+
+// Rasterize `grapheme` with `font` at `size`, with the pen `offset` quarter
+// pixels into a pixel.
+fn rasterize(
+    font: &FontGroup,
+    size: f32,
+    grapheme: &str,
+    offset: (u8, u8),
+) -> Glyph {
+    let (x, y) = (f32::from(offset.0) / 4.0, f32::from(offset.1) / 4.0);
+    let mut path = font.render(grapheme, (x, y), (size, size));
+    // The path reuses one `PathOp` for every item, so each has to be copied.
+    let ops: Vec<PathOp> = (&mut path).map(copy).collect();
+    let mut bounds = Bounds::default();
+    for op in &ops {
+        bounds.add(op);
+    }
+
+    let (bx, by, bw, bh) = bounds.bbox();
+    let empty = Glyph {
+        mask: vec![],
+        x: 0,
+        y: 0,
+        w: 0,
+        h: 0,
+        used: 0,
+    };
+    if bw <= 0.0 || bh <= 0.0 {
+        return empty;
+    }
+    // Leave a pixel around the glyph, so nothing's cut off.  Footile fills
+    // rows 8 pixels at a time (past the end of the last row), so widths are
+    // a multiple of 8.
+    let (x, y) = (bx.floor() as i32 - 1, by.floor() as i32 - 1);
+    let w = ((bx + bw).ceil() as i32 + 1 - x) as usize;
+    let w = (w + 7) & !7;
+    let h = ((by + bh).ceil() as i32 + 1 - y) as usize;
+
+    let shift = [1.0, 0.0, 0.0, 1.0, -x as f32, -y as f32];
+    let mut ops: Vec<PathOp> =
+        ops.iter().map(|op| transform::path_op(shift, op)).collect();
+    // Footile drops the last (partial) row of a glyph, unless the path goes
+    // further down, so add an empty line to the bottom.
+    ops.push(PathOp::Move(0.0, 0.0));
+    ops.push(PathOp::Line(0.0, h as f32));
+    ops.push(PathOp::Close());
+    let mut plotter = footile::Plotter::new(w as u32, h as u32);
+    let mask = plotter.fill(&ops, footile::FillRule::NonZero).pixels().to_vec();
+
+    Glyph {
+        mask,
+        x,
+        y,
+        w,
+        h,
+        ..empty
+    }
+}
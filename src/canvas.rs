@@ -0,0 +1,168 @@
+//! An image that owns its pixels.
+
+use crate::{
+    Error, FontGroup, Format, Image, Paint, PathOp, Pattern, Size, TexCoord,
+};
+
+// Drawing on a canvas can't fail, because its pixels are always the size of
+// its image.
+const SIZED: &str = "canvas pixels are the size of the image";
+
+/// An `Image` with its own pixels, so there's no buffer to pass to
+/// each drawing method (or to get the wrong size).
+///
+/// ```
+/// use barg::{Canvas, Line, Move, Size};
+///
+/// let mut canvas = Canvas::new(Size(64, 64));
+/// let square = [
+///     Move(0.0, 0.0),
+///     Line(32.0, 0.0),
+///     Line(32.0, 32.0),
+///     Line(0.0, 32.0),
+/// ];
+///
+/// // Transforms and clips are set on the image.
+/// canvas.image_mut().translate(16.0, 16.0);
+/// canvas.fill([0, 0, 0, 255], &square);
+/// assert_ne!(canvas.pixels()[(20 * 64 + 20) * 4 + 3], 0);
+/// ```
+pub struct Canvas {
+    image: Image,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    /// Create a new canvas, with all pixels transparent (sRGBA).
+    pub fn new(size: Size) -> Self {
+        Canvas::with_format(size, Format::Rgba8)
+    }
+
+    /// Create a new canvas, with all pixels transparent (or black, for
+    /// formats without alpha) and stored in `format`.
+    pub fn with_format(size: Size, format: Format) -> Self {
+        let image = Image::with_format(size, format);
+        let pixels = vec![0; bytes(size, format)];
+
+        Canvas { image, pixels }
+    }
+
+    /// Create a new canvas from `pixels` (sRGBA).  Returns an error if
+    /// `pixels` isn't `width * height * 4` bytes.
+    pub fn from_pixels(size: Size, pixels: Vec<u8>) -> Result<Self, Error> {
+        let expected = bytes(size, Format::Rgba8);
+        if pixels.len() != expected {
+            return Err(Error::BufferSize {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+
+        Ok(Canvas {
+            image: Image::new(size),
+            pixels,
+        })
+    }
+
+    /// Get the size of the canvas.
+    pub fn size(&self) -> Size {
+        self.image.size()
+    }
+
+    /// Get the image, which has the fill rule, stroke style, transform and
+    /// clip.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Get the image to change its fill rule, stroke style, transform or
+    /// clip.
+    pub fn image_mut(&mut self) -> &mut Image {
+        &mut self.image
+    }
+
+    /// Get the pixels (in the image's `Format`), row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Get the pixels (in the image's `Format`) to change them.
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    /// Get both the image and the pixels, for passing to `Gui` methods.
+    pub fn parts_mut(&mut self) -> (&mut Image, &mut [u8]) {
+        (&mut self.image, &mut self.pixels)
+    }
+
+    /// Get a pattern of the canvas's pixels, to fill with.
+    pub fn pattern(&self) -> Pattern<'_> {
+        Pattern::new(&self.pixels, self.size()).format(self.image.format())
+    }
+
+    /// Take the pixels out of the canvas.
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /// Clear the canvas (see `Image::clear()`).
+    pub fn clear(&mut self) {
+        self.image.clear(&mut self.pixels).expect(SIZED)
+    }
+
+    /// Draw a path with a color (sRGBA) or other `Paint` (see
+    /// `Image::fill()`).
+    pub fn fill<'b, 'p, T, P>(&mut self, paint: P, path: T)
+    where
+        T: IntoIterator<Item = &'b PathOp>,
+        P: Into<Paint<'p>>,
+    {
+        self.image.fill(paint, path, &mut self.pixels).expect(SIZED)
+    }
+
+    /// Stroke a path with a color (sRGBA) or other `Paint` (see
+    /// `Image::stroke()`).
+    pub fn stroke<'b, 'p, T, P>(&mut self, paint: P, path: T)
+    where
+        T: IntoIterator<Item = &'b PathOp>,
+        P: Into<Paint<'p>>,
+    {
+        self.image.stroke(paint, path, &mut self.pixels).expect(SIZED)
+    }
+
+    /// Draw a path with a texture, mapped with `TexCoord`s (see
+    /// `Image::fill_textured()`).  Returns an error if the `texcoords` don't
+    /// fit the path.
+    pub fn fill_textured<'b, T>(
+        &mut self,
+        texture: Pattern,
+        path: T,
+        texcoords: &[TexCoord],
+    ) -> Result<(), Error>
+    where
+        T: IntoIterator<Item = &'b PathOp>,
+    {
+        self.image
+            .fill_textured(texture, path, texcoords, &mut self.pixels)
+    }
+
+    /// Draw text with a color (sRGBA) or other `Paint` (see `Image::text()`).
+    /// Returns where the pen ends up.
+    pub fn text<'p, P: Into<Paint<'p>>>(
+        &mut self,
+        paint: P,
+        xysize: (f32, f32, f32),
+        font: &FontGroup,
+        text: &str,
+    ) -> (f32, f32) {
+        self.image
+            .text(paint, xysize, font, text, &mut self.pixels)
+            .expect(SIZED)
+    }
+}
+
+// Get the number of bytes of pixels in an image.
+fn bytes(size: Size, format: Format) -> usize {
+    usize::from(size.0) * usize::from(size.1) * format.bytes_per_pixel()
+}
@@ -0,0 +1,168 @@
+//! Clips, which limit where an `Image` draws.
+
+use crate::Area;
+
+// Where drawing can change pixels.  Rectangles are kept as rectangles, so
+// drawing only has to look at the pixels within them.
+pub(crate) struct Clip {
+    // Pixels (x0, y0, x1, y1) the clip can cover, within the image.
+    bounds: (usize, usize, usize, usize),
+    shape: Shape,
+}
+
+enum Shape {
+    // A rectangle (x0, y0, x1, y1) in pixels, partly covering the pixels on
+    // its edges.
+    Rect(f32, f32, f32, f32),
+    // Coverage (0 to 255) for each pixel within the bounds.
+    Mask(Vec<u8>),
+}
+
+impl Clip {
+    // Clip to a `rect` (x0, y0, x1, y1) in pixels, on an image of `size`
+    // (width, height).
+    pub(crate) fn rect(
+        rect: (f32, f32, f32, f32),
+        size: (usize, usize),
+    ) -> Self {
+        let (x0, y0, x1, y1) = rect;
+        let (x1, y1) = (x1.max(x0), y1.max(y0));
+        let bounds = (
+            pixel(x0.floor(), size.0),
+            pixel(y0.floor(), size.1),
+            pixel(x1.ceil(), size.0),
+            pixel(y1.ceil(), size.1),
+        );
+
+        Clip {
+            bounds,
+            shape: Shape::Rect(x0, y0, x1, y1),
+        }
+    }
+
+    // Clip to a `mask`, with coverage (0 to 255) for each pixel of an image
+    // `width` pixels wide.  Only the rows and columns with coverage are kept.
+    pub(crate) fn mask(mask: &[u8], width: usize) -> Self {
+        let (mut x0, mut y0, mut x1, mut y1) = (width, 0, 0, 0);
+        for (y, row) in mask.chunks_exact(width.max(1)).enumerate() {
+            let first = row.iter().position(|c| *c != 0);
+            let last = row.iter().rposition(|c| *c != 0);
+            if let (Some(first), Some(last)) = (first, last) {
+                if x1 == 0 {
+                    y0 = y;
+                }
+                x0 = x0.min(first);
+                x1 = x1.max(last + 1);
+                y1 = y + 1;
+            }
+        }
+        if x1 == 0 {
+            return Clip {
+                bounds: (0, 0, 0, 0),
+                shape: Shape::Mask(vec![]),
+            };
+        }
+
+        let mut coverage = Vec::with_capacity((x1 - x0) * (y1 - y0));
+        for row in y0..y1 {
+            coverage.extend_from_slice(&mask[row * width + x0..][..x1 - x0]);
+        }
+        Clip {
+            bounds: (x0, y0, x1, y1),
+            shape: Shape::Mask(coverage),
+        }
+    }
+
+    // Get the part of this clip that's within `outer`.
+    pub(crate) fn within(self, outer: &Clip) -> Self {
+        let (x0, y0, x1, y1) = self.bounds;
+        let (ox0, oy0, ox1, oy1) = outer.bounds;
+        let (x0, y0) = (x0.max(ox0), y0.max(oy0));
+        let bounds = (x0, y0, x1.min(ox1).max(x0), y1.min(oy1).max(y0));
+
+        if let (Shape::Rect(x0, y0, x1, y1), Shape::Rect(ox0, oy0, ox1, oy1)) =
+            (&self.shape, &outer.shape)
+        {
+            let (x0, y0) = (x0.max(*ox0), y0.max(*oy0));
+            let (x1, y1) = (x1.min(*ox1).max(x0), y1.min(*oy1).max(y0));
+            return Clip {
+                bounds,
+                shape: Shape::Rect(x0, y0, x1, y1),
+            };
+        }
+
+        let (x, y, w, h) = (x0, y0, bounds.2 - x0, bounds.3 - y0);
+        let mut coverage = vec![255; w * h];
+        self.apply(&mut coverage, (x, y, w));
+        outer.apply(&mut coverage, (x, y, w));
+        Clip {
+            bounds,
+            shape: Shape::Mask(coverage),
+        }
+    }
+
+    // Get the area (x, y, width, height) of the image the clip can cover.
+    pub(crate) fn area(&self) -> (usize, usize, usize, usize) {
+        let (x0, y0, x1, y1) = self.bounds;
+        (x0, y0, x1 - x0, y1 - y0)
+    }
+
+    // Get the coverage (0 to 255) for each pixel of the clip's area.
+    pub(crate) fn coverage(&self) -> Vec<u8> {
+        let (x, y, w, h) = self.area();
+        let mut coverage = vec![255; w * h];
+        self.apply(&mut coverage, (x, y, w));
+        coverage
+    }
+
+    // Scale `coverage` (0 to 255) for each pixel of an `area` (x, y, width)
+    // of the image by the clip.
+    pub(crate) fn apply(&self, coverage: &mut [u8], (x, y, w): Area) {
+        if w == 0 {
+            return;
+        }
+        match &self.shape {
+            Shape::Rect(x0, y0, x1, y1) => {
+                let columns: Vec<f32> =
+                    (x..x + w).map(|px| overlap(px, *x0, *x1)).collect();
+                for (line, py) in coverage.chunks_exact_mut(w).zip(y..) {
+                    let cy = overlap(py, *y0, *y1);
+                    for (c, cx) in line.iter_mut().zip(&columns) {
+                        *c = scale(*c, (cx * cy * 255.0).round() as u8);
+                    }
+                }
+            }
+            Shape::Mask(mask) => {
+                let (x0, y0, x1, y1) = self.bounds;
+                for (line, py) in coverage.chunks_exact_mut(w).zip(y..) {
+                    for (c, px) in line.iter_mut().zip(x..) {
+                        let inside = (x0..x1).contains(&px)
+                            && (y0..y1).contains(&py);
+                        let m = if inside {
+                            mask[(py - y0) * (x1 - x0) + px - x0]
+                        } else {
+                            0
+                        };
+                        *c = scale(*c, m);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Convert a (whole) coordinate to a pixel, within 0 and `end`.
+fn pixel(v: f32, end: usize) -> usize {
+    v.max(0.0).min(end as f32) as usize
+}
+
+// Get how much of pixel `i` is between `start` and `end`.
+fn overlap(i: usize, start: f32, end: f32) -> f32 {
+    let i = i as f32;
+    (end.min(i + 1.0) - start.max(i)).max(0.0)
+}
+
+// Scale coverage `c` by clip coverage `m` (both 0 to 255).
+fn scale(c: u8, m: u8) -> u8 {
+    ((u32::from(c) * u32::from(m) + 127) / 255) as u8
+}
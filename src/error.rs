@@ -0,0 +1,84 @@
+//! Errors from drawing, saving, loading and comparing images.
+
+use std::path::PathBuf;
+use std::{fmt, io};
+
+/// An error from drawing on, saving, loading or comparing an `Image`.
+#[derive(Debug)]
+pub enum Error {
+    /// The pixel buffer passed in isn't the size of the image (width × height
+    /// pixels of its `Format`).
+    BufferSize {
+        /// How many bytes the buffer should be.
+        expected: usize,
+        /// How many bytes the buffer is.
+        actual: usize,
+    },
+    /// The number of `TexCoord`s passed to `Image::fill_textured()` isn't
+    /// the number of points in the path.
+    TexCoords {
+        /// How many points the path has.
+        points: usize,
+        /// How many texture coordinates were passed.
+        texcoords: usize,
+    },
+    /// The path passed to `Image::fill_textured()` doesn't have 3 points
+    /// that aren't in a line, so its `TexCoord`s can't be mapped.
+    DegenerateTexCoords,
+    /// Reading or writing a file failed, or its data is invalid.
+    Io(io::Error),
+    /// A rendered image doesn't match its reference image (see `Golden`).
+    Mismatch {
+        /// How many pixels are different.
+        pixels: usize,
+        /// Where the image of the differences was saved.
+        diff: PathBuf,
+    },
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::BufferSize { expected, actual } => write!(
+                f,
+                "pixel buffer is {} bytes, but the image needs {}",
+                actual, expected
+            ),
+            Error::TexCoords { points, texcoords } => write!(
+                f,
+                "path has {} points, but {} texture coordinates",
+                points, texcoords
+            ),
+            Error::DegenerateTexCoords => write!(
+                f,
+                "path doesn't have 3 points that aren't in a line to map the \
+                 texture with"
+            ),
+            Error::Io(ref error) => error.fmt(f),
+            Error::Mismatch { pixels, ref diff } => write!(
+                f,
+                "{} pixels are different from the reference (see {})",
+                pixels,
+                diff.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::BufferSize { .. }
+            | Error::TexCoords { .. }
+            | Error::DegenerateTexCoords
+            | Error::Mismatch { .. } => None,
+            Error::Io(ref error) => Some(error),
+        }
+    }
+}
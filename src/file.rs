@@ -0,0 +1,97 @@
+//! Saving and loading PNG files (with the `png` feature).
+
+use crate::{Canvas, Error, Image, Size};
+use png::HasParameters;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+impl Image {
+    /// Save `pixels` of the image to a PNG file (as sRGBA, whatever the
+    /// image's `Format`).  Returns an error if
+    /// `pixels` isn't the size of the image, or the file can't be
+    /// written.
+    pub fn save_png<P: AsRef<Path>>(
+        &self,
+        pixels: &[u8],
+        path: P,
+    ) -> Result<(), Error> {
+        self.check(pixels)?;
+        let file = File::create(path)?;
+        self.write_png(pixels, BufWriter::new(file))
+    }
+
+    /// Write `pixels` of the image as PNG data (see `save_png()`).  Returns an
+    /// error if `pixels` isn't the size of the image, or writing fails.
+    pub fn write_png<W: Write>(
+        &self,
+        pixels: &[u8],
+        writer: W,
+    ) -> Result<(), Error> {
+        self.check(pixels)?;
+        let Size(w, h) = self.size();
+        let mut encoder = png::Encoder::new(writer, w.into(), h.into());
+        encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::from)?;
+        let rgba = self.format().to_rgba(pixels);
+        writer.write_image_data(&rgba).map_err(io::Error::from)?;
+        Ok(())
+    }
+}
+
+impl Canvas {
+    /// Load a canvas from a PNG file.  Any color type is converted to sRGBA
+    /// with 8 bits per channel.  Use `pattern()` to fill with it.
+    pub fn load_png<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        Canvas::read_png(BufReader::new(file))
+    }
+
+    /// Read a canvas from PNG data (see `load_png()`).
+    pub fn read_png<R: Read>(reader: R) -> Result<Self, Error> {
+        let decoder = png::Decoder::new(reader);
+        let (info, mut reader) = decoder.read_info().map_err(io::Error::from)?;
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data).map_err(io::Error::from)?;
+
+        let invalid =
+            |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let too_big = |_| invalid("PNG is wider or taller than 65535 pixels");
+        let w = u16::try_from(info.width).map_err(too_big)?;
+        let h = u16::try_from(info.height).map_err(too_big)?;
+
+        // The decoder expands palettes and strips 16 bit channels, so only
+        // the number of channels is left to convert.
+        let pixels = match info.color_type {
+            png::ColorType::RGBA => data,
+            png::ColorType::RGB => data
+                .chunks_exact(3)
+                .flat_map(|p| vec![p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => data
+                .chunks_exact(2)
+                .flat_map(|p| vec![p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => data
+                .iter()
+                .flat_map(|p| vec![*p, *p, *p, 255])
+                .collect(),
+            png::ColorType::Indexed => {
+                return Err(invalid("PNG palette wasn't expanded").into())
+            }
+        };
+
+        Canvas::from_pixels(Size(w, h), pixels)
+    }
+
+    /// Save the canvas to a PNG file.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.image().save_png(self.pixels(), path)
+    }
+
+    /// Write the canvas as PNG data.
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), Error> {
+        self.image().write_png(self.pixels(), writer)
+    }
+}
@@ -0,0 +1,150 @@
+//! Pixel formats for an `Image`.
+
+/// How the pixels of an `Image` are stored.  Colors are always given in
+/// sRGBA, and converted to the format when drawing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    /// Red, green, blue and alpha, 8 bits each (the default).
+    Rgba8,
+    /// Blue, green, red and alpha, 8 bits each (common for window system
+    /// framebuffers).
+    Bgra8,
+    /// Red, green, blue and alpha, 8 bits each, with red, green and blue
+    /// multiplied by alpha.
+    Rgba8Premultiplied,
+    /// 8 bit grayscale (the luma of colors), such as for masks.
+    Gray8,
+    /// Red (5 bits), green (6 bits) and blue (5 bits) in a little-endian
+    /// 16 bit integer.
+    Rgb565,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::Rgba8
+    }
+}
+
+impl Format {
+    /// Get how many bytes each pixel takes.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Format::Rgba8 | Format::Bgra8 | Format::Rgba8Premultiplied => 4,
+            Format::Gray8 => 1,
+            Format::Rgb565 => 2,
+        }
+    }
+
+    // Get a pixel as RGBA, without removing premultiplied alpha.  Formats
+    // without alpha are opaque.
+    pub(crate) fn load(self, pixel: &[u8]) -> [u8; 4] {
+        match self {
+            Format::Rgba8 | Format::Rgba8Premultiplied => {
+                [pixel[0], pixel[1], pixel[2], pixel[3]]
+            }
+            Format::Bgra8 => [pixel[2], pixel[1], pixel[0], pixel[3]],
+            Format::Gray8 => [pixel[0], pixel[0], pixel[0], 255],
+            Format::Rgb565 => {
+                let p = u16::from_le_bytes([pixel[0], pixel[1]]);
+                let (r, g, b) = (p >> 11, (p >> 5) & 0x3F, p & 0x1F);
+                // Repeat the high bits in the low bits, so white stays white.
+                let r = (r << 3 | r >> 2) as u8;
+                let g = (g << 2 | g >> 4) as u8;
+                let b = (b << 3 | b >> 2) as u8;
+                [r, g, b, 255]
+            }
+        }
+    }
+
+    // Set a pixel from RGBA (already premultiplied, for premultiplied
+    // formats).
+    pub(crate) fn store(self, color: [u8; 4], pixel: &mut [u8]) {
+        let [r, g, b, a] = color;
+        match self {
+            Format::Rgba8 | Format::Rgba8Premultiplied => {
+                pixel.copy_from_slice(&color)
+            }
+            Format::Bgra8 => pixel.copy_from_slice(&[b, g, r, a]),
+            Format::Gray8 => pixel[0] = luma(color),
+            Format::Rgb565 => {
+                let (r, g, b) = (u16::from(r), u16::from(g), u16::from(b));
+                // Round to the nearest value that fits.
+                let r = (r * 31 + 127) / 255;
+                let g = (g * 63 + 127) / 255;
+                let b = (b * 31 + 127) / 255;
+                pixel.copy_from_slice(&(r << 11 | g << 5 | b).to_le_bytes());
+            }
+        }
+    }
+
+    // Composite a `color` (sRGBA) with `alpha` (its alpha scaled by
+    // coverage, 0 to 255) over a pixel.
+    pub(crate) fn over(self, color: [u8; 4], alpha: u32, pixel: &mut [u8]) {
+        let dst = self.load(pixel);
+        let mut out = [0; 4];
+        if self.is_straight() {
+            // Weigh each color by how much it shows, and divide by the alpha
+            // of the result.
+            let src_weight = alpha * 255;
+            let dst_weight = u32::from(dst[3]) * (255 - alpha);
+            let total = src_weight + dst_weight;
+            if total == 0 {
+                return;
+            }
+            for ((o, s), d) in out.iter_mut().zip(&color).zip(&dst).take(3) {
+                let sum = u32::from(*s) * src_weight
+                    + u32::from(*d) * dst_weight
+                    + total / 2;
+                *o = (sum / total) as u8;
+            }
+            out[3] = ((total + 127) / 255) as u8;
+        } else {
+            // The source scaled by `alpha` is premultiplied, and formats
+            // without alpha are opaque.
+            for (i, (o, d)) in out.iter_mut().zip(&dst).enumerate() {
+                let src = if i == 3 { 255 } else { u32::from(color[i]) };
+                let dst = u32::from(*d);
+                *o = ((src * alpha + dst * (255 - alpha) + 127) / 255) as u8;
+            }
+        }
+        self.store(out, pixel);
+    }
+
+    // Check if color channels are stored without multiplying by alpha (in a
+    // format with alpha).
+    pub(crate) fn is_straight(self) -> bool {
+        self == Format::Rgba8 || self == Format::Bgra8
+    }
+
+    // Get a pixel as sRGBA (without premultiplied alpha).
+    pub(crate) fn rgba(self, pixel: &[u8]) -> [u8; 4] {
+        let [r, g, b, a] = self.load(pixel);
+        if self != Format::Rgba8Premultiplied || a == 0 || a == 255 {
+            return [r, g, b, a];
+        }
+        let divide = |c: u8| {
+            let c = (u32::from(c) * 255 + u32::from(a) / 2) / u32::from(a);
+            c.min(255) as u8
+        };
+        [divide(r), divide(g), divide(b), a]
+    }
+
+    // Convert pixels in this format to sRGBA (for PNG files).
+    #[cfg(feature = "png")]
+    pub(crate) fn to_rgba(self, pixels: &[u8]) -> Vec<u8> {
+        if self == Format::Rgba8 {
+            return pixels.to_vec();
+        }
+        pixels
+            .chunks_exact(self.bytes_per_pixel())
+            .flat_map(|pixel| self.rgba(pixel).to_vec())
+            .collect()
+    }
+}
+
+// Get the luma of a color (ignoring alpha), weighted like the sRGB primaries.
+fn luma(color: [u8; 4]) -> u8 {
+    let [r, g, b, _] = color;
+    let (r, g, b) = (u32::from(r), u32::from(g), u32::from(b));
+    ((r * 2126 + g * 7152 + b * 722 + 5000) / 10000) as u8
+}
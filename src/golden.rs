@@ -0,0 +1,138 @@
+//! Golden image tests (with the `png` feature).
+
+use crate::{Canvas, Error, Size};
+use std::env;
+use std::path::PathBuf;
+
+/// A reference ("golden") PNG image to compare rendered canvases with, for
+/// catching rendering changes in tests.
+///
+/// If the canvas doesn't match, `check()` saves it next to the reference as
+/// `NAME.actual.png`, along with `NAME.diff.png`, which shows pixels that are
+/// different in red over a faded copy of the reference.  To create or update
+/// references, run the tests with the `BARG_BLESS` environment variable set
+/// (or call `bless()`).
+///
+/// ```no_run
+/// use barg::{Canvas, FontGroup, Golden, Gui, Row, Size};
+///
+/// let mut gui = Gui::new(FontGroup::default());
+/// let mut canvas = Canvas::new(Size(320, 180));
+/// let (image, pixels) = canvas.parts_mut();
+/// gui.page(image, pixels, &|row| match row {
+///     0 => Some(Row::default().text("Hello").toggle(true)),
+///     _ => None,
+/// })
+/// .unwrap();
+///
+/// Golden::new("tests/golden/settings.png")
+///     .tolerance(2)
+///     .check(&canvas)
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Golden {
+    // Path of the reference PNG.
+    path: PathBuf,
+    // How much a channel can be off by before the pixel is different.
+    tolerance: u8,
+}
+
+impl Golden {
+    /// Create a golden image test, with the reference PNG at `path` and no
+    /// tolerance.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Golden {
+            path: path.into(),
+            tolerance: 0,
+        }
+    }
+
+    /// Set how much each channel (0 to 255) of a pixel can be different from
+    /// the reference before the pixel counts as different.
+    pub fn tolerance(self, tolerance: u8) -> Self {
+        Golden { tolerance, ..self }
+    }
+
+    /// Compare `canvas` with the reference (see `compare()`).  With
+    /// `BARG_BLESS` set, saves `canvas` as the reference instead (see
+    /// `bless()`).
+    pub fn check(&self, canvas: &Canvas) -> Result<(), Error> {
+        if env::var_os("BARG_BLESS").is_some() {
+            return self.bless(canvas);
+        }
+        self.compare(canvas)
+    }
+
+    /// Save `canvas` as the reference.
+    pub fn bless(&self, canvas: &Canvas) -> Result<(), Error> {
+        canvas.save_png(&self.path)
+    }
+
+    /// Compare `canvas` with the reference, whether or not `BARG_BLESS` is
+    /// set.  Returns `Error::Mismatch` if any pixels are different (every
+    /// pixel is, if the size is), or an error if the reference can't be
+    /// loaded.
+    pub fn compare(&self, canvas: &Canvas) -> Result<(), Error> {
+        let reference = Canvas::load_png(&self.path)?;
+        let (pixels, diff) = self.diff(canvas, &reference);
+        if pixels == 0 {
+            return Ok(());
+        }
+
+        let diff_path = self.sibling("diff");
+        canvas.save_png(self.sibling("actual"))?;
+        diff.save_png(&diff_path)?;
+        Err(Error::Mismatch {
+            pixels,
+            diff: diff_path,
+        })
+    }
+
+    // Count the pixels of `canvas` that are different from `reference`, and
+    // draw an image of the differences.
+    fn diff(&self, canvas: &Canvas, reference: &Canvas) -> (usize, Canvas) {
+        const RED: [u8; 4] = [255, 0, 0, 255];
+        let mut diff = Canvas::new(canvas.size());
+
+        // Every pixel is different if the size is.
+        if canvas.size() != reference.size() {
+            for out in diff.pixels_mut().chunks_exact_mut(4) {
+                out.copy_from_slice(&RED);
+            }
+            let Size(w, h) = canvas.size();
+            return (usize::from(w) * usize::from(h), diff);
+        }
+
+        // Compare as sRGBA, since the reference was loaded as sRGBA.
+        let actual = canvas.image().format().to_rgba(canvas.pixels());
+        let tolerance = i16::from(self.tolerance);
+        let mut pixels = 0;
+        let pairs = actual
+            .chunks_exact(4)
+            .zip(reference.pixels().chunks_exact(4));
+        for ((a, e), out) in pairs.zip(diff.pixels_mut().chunks_exact_mut(4)) {
+            let close = a.iter().zip(e).all(|(a, e)| {
+                (i16::from(*a) - i16::from(*e)).abs() <= tolerance
+            });
+            if close {
+                // Faded, so the differences stand out.
+                let sum = u16::from(e[0]) + u16::from(e[1]) + u16::from(e[2]);
+                let gray = (sum / 12) as u8 + 191;
+                out.copy_from_slice(&[gray, gray, gray, 255]);
+            } else {
+                out.copy_from_slice(&RED);
+                pixels += 1;
+            }
+        }
+        (pixels, diff)
+    }
+
+    // Get the path of a file next to the reference, `NAME.suffix.png`.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default();
+        let mut name = stem.to_os_string();
+        name.push(format!(".{}.png", suffix));
+        self.path.with_file_name(name)
+    }
+}
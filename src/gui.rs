@@ -1,87 +1,263 @@
+use crate::Ellipsis;
+use crate::Error;
 use crate::FontGroup;
 use crate::Image;
+use crate::text;
 use fonterator::PathOp::{self, *};
 
-type Generator<'b> = Fn(
-            usize,
-            &mut [u8; 5],
-        )
-            -> &'b [(&'b [([u8; 4], &'b [PathOp])], &'b str)];
+type Generator<'b> = dyn Fn(usize) -> Option<Row<'b>> + 'b;
+type SwipeFn = dyn FnMut(bool, bool, f32, f32);
 
 // Import entity component system for use.
-// mod ecs;
-// use self::ecs::*;
+mod ecs;
+use self::ecs::*;
+
+mod text_field;
+mod theme;
+mod widget;
+pub use self::text_field::TextField;
+pub use self::theme::{contrast, luminance, Theme};
+pub use self::widget::{Row, Widget};
 
-/*mod components {
-    use fonterator::PathOp;
+mod components {
+    use super::{Region, SwipeFn};
 
-    #[derive(Clone)]
+    #[derive(Default)]
     pub struct Widget {
-        pub(super) path: Vec<PathOp>,
-        pub(super) text: String,
+        // Region, row and column (or whole row if `None`) of the widget.
+        pub(super) at: Option<(Region, usize, Option<usize>)>,
     }
 
-    #[derive(Clone)]
+    #[derive(Default)]
     pub struct Press {
-        pub(super) press: Option<fn()>,
+        pub(super) press: Option<Box<dyn FnMut()>>,
     }
 
-    #[derive(Clone)]
+    #[derive(Default)]
     pub struct Swipe {
         // Function executes if press and drag.  `edge` is true if the user started pressing near
         // the edge of the window.  `done` is true if the swipe was successful.  `amount` is
         // -1 for all the way up or left, 1 for all the way down or right.  0 for release and no
         //  swipe event. "All the way" refers to the length of the screen.
-        pub(super) swipe: Option<fn(edge: bool, done: bool, x_amount: f32, y_amount: f32)>,
+        pub(super) swipe: Option<Box<SwipeFn>>,
     }
-}*/
+}
+
+/// User input for `Gui::input()` and `TextField::input()`.  Coordinates are
+/// in pixels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Input {
+    /// Start pressing at (x, y).
+    Press(f32, f32),
+    /// Drag the press to (x, y).
+    Drag(f32, f32),
+    /// Stop pressing at (x, y).
+    Release(f32, f32),
+    /// Type a character.
+    Text(char),
+    /// Press an editing key, selecting (shift held down) if `true`.
+    Edit(Edit, bool),
+}
+
+/// Editing keys for `Input::Edit`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Edit {
+    /// Delete the selection, or the character before the cursor.
+    Backspace,
+    /// Delete the selection, or the character after the cursor.
+    Delete,
+    /// Move the cursor back one character.
+    Left,
+    /// Move the cursor forward one character.
+    Right,
+    /// Move the cursor to the start of the text.
+    Home,
+    /// Move the cursor to the end of the text.
+    End,
+}
+
+// A press that hasn't been released yet.
+struct Pointer {
+    // Where the press started.
+    x: f32,
+    y: f32,
+    // Which widget was pressed.
+    id: Option<Id>,
+    // If the press started near the edge of the window.
+    edge: bool,
+    // If the press has been dragged far enough to be a swipe.
+    swiping: bool,
+}
+
+/// A region of the `Gui`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Region {
+    /// The non-scrolling head.
+    Head,
+    /// The scrolling page.
+    Page,
+    /// The scrolling sidebar.
+    Sidebar,
+    /// The non-scrolling centered popup.
+    Popup,
+}
+
+/// How wide a column in a row is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Width {
+    /// A fixed width in pixels.
+    Fixed(f32),
+    /// A fraction of the width of the row (0 to 1).
+    Fraction(f32),
+    /// Just wide enough to fit the column's graphic and text.
+    Fit,
+}
+
+/// Where the graphic and text of a column go within the column.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Align {
+    /// Against the left edge of the column.
+    Left,
+    /// In the center of the column.
+    Center,
+    /// Against the right edge of the column.
+    Right,
+}
+
+/// Layout rules for a column in a row.  The default is `Width::Fit`,
+/// `Align::Left` and `Ellipsis::End`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Column {
+    /// How wide the column is.
+    pub width: Width,
+    /// Where the column's content goes within the column.
+    pub align: Align,
+    /// Where text (and button labels) too wide for the column are shortened.
+    pub ellipsis: Ellipsis,
+}
+
+impl Column {
+    /// Create new column layout rules, with `Ellipsis::End`.
+    pub fn new(width: Width, align: Align) -> Self {
+        Column {
+            width,
+            align,
+            ellipsis: Ellipsis::End,
+        }
+    }
+
+    /// Set where text too wide for the column is shortened.
+    pub fn ellipsis(self, ellipsis: Ellipsis) -> Self {
+        Column { ellipsis, ..self }
+    }
+}
+
+impl Default for Column {
+    fn default() -> Self {
+        Column::new(Width::Fit, Align::Left)
+    }
+}
+
+/// Which side of the window the sidebar is on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Side {
+    /// Left edge of the window.
+    Left,
+    /// Right edge of the window.
+    Right,
+}
+
+/// What's at a pixel on the `Gui`, returned from `Gui::hit()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    /// Which region was hit.
+    pub region: Region,
+    /// Which row was hit (the row index that was passed to the generator).
+    pub row: usize,
+    /// Which column was hit (index into the slice returned from the
+    /// generator), or `None` if the row was hit after the last column.
+    pub column: Option<usize>,
+}
+
+// A rendered row, saved for user input.
+struct RowArea {
+    // Left and right of the row.
+    x: f32,
+    x_end: f32,
+    // Top of the row.
+    y: f32,
+    // Bottom of the row.
+    y_end: f32,
+    // Row index passed to the generator.
+    row: usize,
+    // Start x of each column, and then end x of the last column.
+    columns: Vec<f32>,
+}
 
 /// Id for widget.
-// #[derive(Copy, Clone)]
-// pub struct Id(u32);
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Id(u32);
 
-/*impl Into<u32> for Id {
-    fn into(self) -> u32 {
-        self.0
+impl From<Id> for u32 {
+    fn from(id: Id) -> u32 {
+        id.0
     }
-}*/
+}
 
 /// A GUI (Graphical User Interface).
 ///
 /// ```
-/// fn generator(row: usize) -> Option<Widget> {
-///     
-/// }
-/// // Build the GUI.
-/// let mut gui = Gui::new();
-/// // Set the non-scrolling header.
-/// gui.header(generator);
-/// // Set the scrolling page.
-/// gui.page(generator);
-/// // Set the scrolling sidebar.
-/// gui.sidebar(generator);
-/// // Set the non-scrolling centered popup.
-/// gui.popup(generator);
-/// // Set the
+/// use barg::{FontGroup, Gui, Image, Row, Size};
 ///
+/// let mut image = Image::new(Size(640, 360));
+/// let mut buffer = vec![0; 640 * 360 * 4];
+/// let items = ["Apples", "Bananas", "Cherries"];
 ///
-/// // Create an row of entities.
-/// let header = gui.add(0x303030FF, Widget::Row);
+/// // Build the GUI.
+/// let mut gui = Gui::new(FontGroup::default());
+/// // Draw the scrolling page, generating rows as they become visible.
+/// gui.page(&mut image, &mut buffer, &|row| {
+///     let item = items.get(row)?;
+///     Some(Row::new([255, 255, 255, 255]).separator().text(item))
+/// });
+/// // Draw the non-scrolling head.
+/// gui.head(&mut image, &mut buffer, &|_row| {
+///     Some(Row::new([48, 48, 64, 255]).text("Fruit").button("Add"))
+/// });
 /// ```
+///
+/// There's also a scrolling `sidebar` and non-scrolling centered `popup`.
 pub struct Gui<'a> {
     // The data for the entity component system.
-    //    c_widget: Storage<components::Widget, Id>,
-    //    c_press: Storage<components::Press, Id>,
-    //    c_swipe: Storage<components::Swipe, Id>,
+    c_widget: Storage<components::Widget, Id>,
+    c_press: Storage<components::Press, Id>,
+    c_swipe: Storage<components::Swipe, Id>,
+    // Next widget id.
+    next_id: u32,
+    // Current press, if any.
+    pointer: Option<Pointer>,
+    // Size of the image the GUI was last drawn on.
+    wh: (f32, f32),
 
-    // Page scroll amount
-    #[allow(unused)] // TODO
+    // Page scroll amount (in pixels).
     scroll: u32,
-    // GUI Size.
+    // Sidebar scroll amount (in pixels).
+    sidebar_scroll: u32,
+    // Sidebar width (0 for no sidebar), and which side it's on.
+    sidebar: (u32, Side),
+    // If the popup is open (capturing input).
+    popup: bool,
+    // Column layout rules for each region.
+    columns: [Vec<Column>; 4],
+    // Colors and metrics.
+    theme: Theme,
+    // Row height, before scaling.
     size: u32,
-    // Fast user input row height cache.  Start y and then start index.
-    #[allow(unused)] // TODO
-    ydif_id: Vec<(u32, u32)>,
+    // DPI scale factor.
+    scale: f32,
+    // Fast user input row height cache.  Rows of each region, in the order
+    // they were drawn (top to bottom).
+    ydif_id: [Vec<RowArea>; 4],
     //
     font: FontGroup<'a>,
 }
@@ -92,198 +268,900 @@ impl<'a> Gui<'a> {
         Gui {
             // Initially at top of page.
             scroll: 0,
-            // 48 pixels default.
+            // Initially at top of sidebar.
+            sidebar_scroll: 0,
+            // No sidebar.
+            sidebar: (0, Side::Left),
+            // No popup.
+            popup: false,
+            // Fit columns.
+            columns: [vec![], vec![], vec![], vec![]],
+            // Light theme.
+            theme: Theme::default(),
+            // 36 pixels default.
             size: 36,
+            // Not scaled.
+            scale: 1.0,
             //
-            ydif_id: vec![],
+            ydif_id: [vec![], vec![], vec![], vec![]],
             // The font
             font,
-            /*            c_widget: Storage::new(components::Widget {
-                path: vec![],
-                text: "".to_string(),
-            }),
-            c_press: Storage::new(components::Press {
-                press: None,
-            }),
-            c_swipe: Storage::new(components::Swipe {
-                swipe: None,
-            }),
-            ydif_id: Vec::new(),*/
+            // No widgets yet.
+            c_widget: Storage::new(),
+            c_press: Storage::new(),
+            c_swipe: Storage::new(),
+            next_id: 0,
+            // Not pressed.
+            pointer: None,
+            // Not drawn yet.
+            wh: (0.0, 0.0),
         }
     }
 
+    /// Scroll the page by `amount` pixels.  Positive values scroll down, and
+    /// negative values scroll up.  The page can't scroll above the first row.
+    pub fn scroll(&mut self, amount: i32) {
+        scroll(&mut self.scroll, amount);
+    }
+
+    /// Scroll the sidebar by `amount` pixels, independently of the page.
+    /// Positive values scroll down, and negative values scroll up.
+    pub fn scroll_sidebar(&mut self, amount: i32) {
+        scroll(&mut self.sidebar_scroll, amount);
+    }
+
+    /// Set the height of rows, before scaling (36 by default).  Text,
+    /// paddings and widgets are sized relative to it.
+    pub fn set_size(&mut self, size: u32) {
+        self.size = size.max(1);
+    }
+
+    /// Set the DPI scale factor (1 by default), for example 2 on a display
+    /// with twice as many pixels per inch.  Row height, sidebar width,
+    /// `Width::Fixed` columns, spacers, icons and theme metrics are all
+    /// multiplied by it.  Scroll amounts are still in (unscaled) pixels.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    /// Set the `width` in pixels (before scaling) and `side` of the window
+    /// for the sidebar.
+    /// The page gets narrower to make room for it.  A `width` of 0 removes
+    /// the sidebar.
+    pub fn set_sidebar(&mut self, width: u32, side: Side) {
+        self.sidebar = (width, side);
+    }
+
+    /// Set the layout rules for the columns of every row in a `region`.  The
+    /// first rule is for the first column, and so on.  Columns without a rule
+    /// use `Column::default()`.
+    pub fn set_columns(&mut self, region: Region, columns: &[Column]) {
+        self.columns[region as usize] = columns.to_vec();
+    }
+
+    /// Set the colors and metrics used to draw the GUI.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Get the colors and metrics used to draw the GUI.
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Close the popup opened by `popup`, so that input goes to the rest of
+    /// the GUI again.
+    pub fn close_popup(&mut self) {
+        self.popup = false;
+        self.ydif_id[Region::Popup as usize].clear();
+    }
+
+    /// Add a widget at a `row` of a `region`.  If `column` is `None`, then the
+    /// widget is the whole row.  Returns the widget's `Id`.
+    pub fn add(
+        &mut self,
+        region: Region,
+        row: usize,
+        column: Option<usize>,
+    ) -> Id {
+        let id = Id(self.next_id);
+        self.next_id += 1;
+        self.c_widget.set(id, components::Widget {
+            at: Some((region, row, column)),
+        });
+        id
+    }
+
+    /// Remove a widget, along with its press and swipe functions.  The `Id`
+    /// isn't reused.
+    pub fn remove(&mut self, id: Id) {
+        self.c_widget.remove(id);
+        self.c_press.remove(id);
+        self.c_swipe.remove(id);
+        if let Some(pointer) = self.pointer.as_mut() {
+            if pointer.id == Some(id) {
+                pointer.id = None;
+            }
+        }
+    }
+
+    /// Set the function to call when a widget is pressed and released on it
+    /// without swiping.
+    pub fn press(&mut self, id: Id, press: Box<dyn FnMut()>) {
+        self.c_press.set(id, components::Press { press: Some(press) });
+    }
+
+    /// Set the function to call when a widget is pressed and dragged.  `edge`
+    /// is true if the press started near the edge of the window.  `done` is
+    /// false while dragging, and true on release if the swipe was successful.
+    /// `x_amount` and `y_amount` are -1 for all the way up or left, and 1 for
+    /// all the way down or right, where "all the way" is the size of the
+    /// window.  On release without a successful swipe, both amounts are 0.
+    pub fn swipe(
+        &mut self,
+        id: Id,
+        swipe: Box<SwipeFn>,
+    ) {
+        self.c_swipe.set(id, components::Swipe { swipe: Some(swipe) });
+    }
+
+    /// Send user input to the GUI, calling the press and swipe functions of
+    /// the widget under the pointer.  Keyboard input is ignored, and should
+    /// be sent to the focused `TextField` instead.
+    pub fn input(&mut self, input: Input) {
+        let (w, h) = self.wh;
+        // Distance the pointer has to move to start a swipe.
+        let slop = self.row_size() as f32 * 0.25;
+
+        match input {
+            Input::Press(x, y) => {
+                let edge = x < slop
+                    || y < slop
+                    || x > w - slop
+                    || y > h - slop;
+                let id = self.hit(x, y).and_then(|hit| self.widget(hit));
+
+                self.pointer = Some(Pointer {
+                    x,
+                    y,
+                    id,
+                    edge,
+                    swiping: false,
+                });
+            }
+            Input::Drag(x, y) => {
+                let pointer = match self.pointer.as_mut() {
+                    Some(pointer) => pointer,
+                    None => return,
+                };
+                if (x - pointer.x).abs() > slop || (y - pointer.y).abs() > slop
+                {
+                    pointer.swiping = true;
+                }
+                if !pointer.swiping {
+                    return;
+                }
+                let (edge, id) = (pointer.edge, pointer.id);
+                let xa = amount(x - pointer.x, w);
+                let ya = amount(y - pointer.y, h);
+
+                if let Some(id) = id {
+                    if let Some(swipe) = self.swipe_fn(id) {
+                        swipe(edge, false, xa, ya);
+                    }
+                }
+            }
+            Input::Release(x, y) => {
+                let pointer = match self.pointer.take() {
+                    Some(pointer) => pointer,
+                    None => return,
+                };
+                let id = match pointer.id {
+                    Some(id) => id,
+                    None => return,
+                };
+                let moved = (x - pointer.x).abs() > slop
+                    || (y - pointer.y).abs() > slop;
+
+                if pointer.swiping || moved {
+                    let xa = amount(x - pointer.x, w);
+                    let ya = amount(y - pointer.y, h);
+                    if let Some(swipe) = self.swipe_fn(id) {
+                        // A swipe is successful past a quarter of the window.
+                        if xa.abs() >= 0.25 || ya.abs() >= 0.25 {
+                            swipe(pointer.edge, true, xa, ya);
+                        } else {
+                            swipe(pointer.edge, false, 0.0, 0.0);
+                        }
+                    }
+                } else if self.hit(x, y).and_then(|hit| self.widget(hit))
+                    == Some(id)
+                {
+                    // Only a press if released on the same widget.
+                    let press = self.c_press.get_mut(id);
+                    if let Some(press) = press.and_then(|p| p.press.as_mut()) {
+                        press();
+                    }
+                }
+            }
+            Input::Text(_) | Input::Edit(..) => {}
+        }
+    }
+
+    // Get the swipe function of a widget, if it has one.
+    fn swipe_fn(&mut self, id: Id) -> Option<&mut Box<SwipeFn>> {
+        self.c_swipe.get_mut(id).and_then(|s| s.swipe.as_mut())
+    }
+
+    // Find the widget for a hit, preferring a widget for the column over a
+    // widget for the whole row.
+    fn widget(&self, hit: Hit) -> Option<Id> {
+        let column = self.c_widget.find(|w| {
+            w.at == Some((hit.region, hit.row, hit.column))
+        });
+        let row = || {
+            self.c_widget.find(|w| w.at == Some((hit.region, hit.row, None)))
+        };
+
+        column.or_else(row).map(Id)
+    }
+
+    /// Find the region, row and column at pixel (`x`, `y`), from the last
+    /// time each region was drawn.  Returns `None` if no row is there.  While
+    /// the popup is open, only the popup can be hit.
+    pub fn hit(&self, x: f32, y: f32) -> Option<Hit> {
+        // Head is drawn over the page, so check it first.
+        let regions: &[Region] = if self.popup {
+            &[Region::Popup]
+        } else {
+            &[Region::Head, Region::Sidebar, Region::Page]
+        };
+        for region in regions.iter() {
+            // Rows are drawn top to bottom, so binary search on y.
+            let rows = &self.ydif_id[*region as usize];
+            let index = rows.partition_point(|r| r.y_end <= y);
+            let area = match rows.get(index) {
+                Some(area) if area.y <= y && area.x <= x && x < area.x_end => {
+                    area
+                }
+                _ => continue,
+            };
+            let column = area.columns.iter().rposition(|c| *c <= x);
+            let column = match column {
+                Some(c) if c + 1 < area.columns.len() => Some(c),
+                _ => None,
+            };
+
+            return Some(Hit {
+                region: *region,
+                row: area.row,
+                column,
+            });
+        }
+        None
+    }
+
     /// Redraw window head.  For `head`, the `generator` only gets called once.  This is because the
-    /// head is always `gui_size` high.
+    /// head is always `gui_size` high.  If it returns `None`, the head is left
+    /// blank.
     pub fn head<'b>(
         &mut self,
         image: &mut Image,
         buffer: &mut [u8],
         generator: &Generator<'b>,
-    ) {
+    ) -> Result<(), Error> {
+        let crate::Size(w, h) = image.size();
+        self.wh = (f32::from(w), f32::from(h));
+        self.ydif_id[Region::Head as usize].clear();
+
+        // Render From Generator.
+        let row = match generator(0) {
+            Some(row) => row,
+            None => return self.draw_window_border(image, buffer),
+        };
+
+        // Render Row.
+        let (w, height) = (f32::from(w), (self.row_size() + 1) as f32);
+        let area = (0.0, 0.0, w, height);
+        let columns = self.draw_row(image, buffer, Region::Head, area, &row)?;
+        self.ydif_id[Region::Head as usize].push(RowArea {
+            x: 0.0,
+            x_end: w,
+            y: 0.0,
+            y_end: height,
+            row: 0,
+            columns,
+        });
+        self.draw_window_border(image, buffer)
+    }
+
+    /// Redraw window page.  For `page`, the `generator` gets called once for
+    /// each row that is visible at the current scroll position, starting at
+    /// the first visible row and ending when either the bottom of the `image`
+    /// is reached or the `generator` returns `None`.  Rows that are
+    /// scrolled out of view are never generated.
+    ///
+    /// Rows are clipped to the area below the head, and widgets are clipped to
+    /// their row.
+    pub fn page<'b>(
+        &mut self,
+        image: &mut Image,
+        buffer: &mut [u8],
+        generator: &Generator<'b>,
+    ) -> Result<(), Error> {
         let crate::Size(w, _h) = image.size();
-        let mut x = 0.0;
-        let y = 0.0; // Not mutable because we only call generator once.
-        let mut color = [0; 5];
         let w = f32::from(w);
+        let side = self.sidebar.1;
+        let sidebar = self.scaled(self.sidebar.0 as f32);
+        let xs = match side {
+            Side::Left => (sidebar.min(w), w),
+            Side::Right => (0.0, (w - sidebar).max(0.0)),
+        };
 
-        // Render From Generator.
-        let iter = generator(0, &mut color);
-        let bg = [color[0], color[1], color[2], color[3]];
-        let fg = fgcolor_from_bg(bg);
+        self.rows(image, buffer, Region::Page, xs, self.scroll, generator)
+    }
 
-        // Render Background.
-        let shape = [
-            Move(0.0, 0.0),
-            Line(w, 0.0),
-            Line(w, (self.size + 1) as f32),
-            Line(0.0, (self.size + 1) as f32),
-        ];
-        image.fill(bg /*color*/, &shape /*path*/, buffer /**/);
-        draw_window_border(image, buffer, (self.size) as f32, color[4]);
-
-        // Render Slice
-        for (g, t) in iter {
-            // Iterate over columns in row.
-            for p in g.iter() {
-                // Iterate over paths in graphic.
-                image.fill(
-                    p.0, // color
-                    p.1, // path
-                    buffer,
-                );
-            }
+    /// Redraw window sidebar (set up with `set_sidebar`).  The `generator` gets
+    /// called the same way as for `page`, but the sidebar scrolls separately.
+    ///
+    /// Like the page, rows are clipped to the area below the head.
+    pub fn sidebar<'b>(
+        &mut self,
+        image: &mut Image,
+        buffer: &mut [u8],
+        generator: &Generator<'b>,
+    ) -> Result<(), Error> {
+        let crate::Size(w, _h) = image.size();
+        let w = f32::from(w);
+        let side = self.sidebar.1;
+        let sidebar = self.scaled(self.sidebar.0 as f32);
+        let xs = match side {
+            Side::Left => (0.0, sidebar.min(w)),
+            Side::Right => ((w - sidebar).max(0.0), w),
+        };
+        let scroll = self.sidebar_scroll;
 
-            x += image
-                .text(
-                    fg,
-                    (
-                        x + (self.size as f32 * 0.125),
-                        y + (self.size as f32 * 0.125),
-                        self.size as f32 * 0.75,
-                    ),
-                    &self.font,
-                    t,
-                    buffer,
-                )
-                .0;
+        self.rows(image, buffer, Region::Sidebar, xs, scroll, generator)?;
+
+        // Divide the sidebar from the page.
+        let x = match side {
+            Side::Left => xs.1,
+            Side::Right => xs.0,
+        };
+        self.draw_divider(image, buffer, x, self.row_size() as f32)
+    }
+
+    /// Redraw the popup, and open it if it's not open.  The popup is centered
+    /// over the rest of the GUI with the rest of the GUI dimmed behind it, so
+    /// call `popup` last.  The `generator` gets called for each row until it
+    /// returns `None` or the popup fills the window, and the popup is
+    /// sized to fit the rows.  While open, the popup captures all input, until
+    /// `close_popup` is called.
+    pub fn popup<'b>(
+        &mut self,
+        image: &mut Image,
+        buffer: &mut [u8],
+        generator: &Generator<'b>,
+    ) -> Result<(), Error> {
+        let crate::Size(w, h) = image.size();
+        self.wh = (f32::from(w), f32::from(h));
+        let (w, h) = self.wh;
+        let size = self.row_size() as f32;
+
+        // Collect rows that fit, and find the widest.
+        let mut rows = vec![];
+        let mut width = 0.0f32;
+        while (rows.len() + 3) as f32 * size <= h {
+            let row = match generator(rows.len()) {
+                Some(row) => row,
+                None => break,
+            };
+            let row_width = self
+                .layout(Region::Popup, 0.0, &row.widgets)
+                .last()
+                .map(|c| c.0 + c.1)
+                .unwrap_or(0.0);
+            width = width.max(row_width);
+            rows.push(row);
         }
+        let width = width.min(w - 2.0 * size).max(0.0);
+        let height = rows.len() as f32 * size;
+        let (x, y) = ((w - width) * 0.5, (h - height) * 0.5);
+
+        // Dim the rest of the GUI.
+        let [r, g, b, _] = self.theme.border;
+        let shape = rect_path(0.0, 0.0, w, h);
+        image.fill([r, g, b, 128], &shape, buffer)?;
+
+        // Render Rows
+        self.ydif_id[Region::Popup as usize].clear();
+        for (index, row) in rows.iter().enumerate() {
+            let top = y + index as f32 * size;
+            let area = (x, top, width, size);
+            let columns =
+                self.draw_row(image, buffer, Region::Popup, area, row)?;
+            self.ydif_id[Region::Popup as usize].push(RowArea {
+                x,
+                x_end: x + width,
+                y: top,
+                y_end: top + size,
+                row: index,
+                columns,
+            });
+        }
+
+        // Outline the popup.
+        image.stroke(
+            self.theme.border, /*color*/
+            &[
+                PenWidth(self.scaled(self.theme.border_width)),
+                Move(x, y),
+                Line(x + width, y),
+                Line(x + width, y + height),
+                Line(x, y + height),
+                Line(x, y),
+            ], /*path*/
+            buffer, /**/
+        )?;
+        self.popup = true;
+        Ok(())
     }
 
-    /// Redraw window page.  For `page`, the `generator` only gets called infinitely because it can
-    /// scroll.
-    pub fn page<'b>(
+    // Get the height of rows in pixels, after scaling.
+    fn row_size(&self) -> u32 {
+        (self.size as f32 * self.scale).round().max(1.0) as u32
+    }
+
+    // Scale a length in pixels by the DPI scale factor.
+    fn scaled(&self, length: f32) -> f32 {
+        length * self.scale
+    }
+
+    // Draw the window border.
+    fn draw_window_border(
+        &self,
+        image: &mut Image,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        let crate::Size(w, h) = image.size();
+        let w = f32::from(w);
+        let h = f32::from(h);
+
+        image.stroke(
+            self.theme.border, /*color*/
+            &[
+                PenWidth(self.scaled(self.theme.border_width)),
+                Move(0.0, 0.0),
+                Line(w, 0.0),
+                Line(w, h),
+                Line(0.0, h),
+                Line(0.0, 0.0),
+            ], /*path*/
+            buffer, /**/
+        )
+    }
+
+    // Draw a row separator at `y` from `xs.0` to `xs.1`.
+    fn draw_separator(
+        &self,
+        image: &mut Image,
+        buffer: &mut [u8],
+        xs: (f32, f32),
+        y: f32,
+    ) -> Result<(), Error> {
+        let width = self.scaled(self.theme.separator_width);
+        image.stroke(
+            self.theme.separator, /*color*/
+            &[PenWidth(width), Move(xs.0, y), Line(xs.1, y), Close()], /*path*/
+            buffer, /**/
+        )
+    }
+
+    // Draw a vertical divider at `x` from `y` to the bottom of the image.
+    fn draw_divider(
+        &self,
+        image: &mut Image,
+        buffer: &mut [u8],
+        x: f32,
+        y: f32,
+    ) -> Result<(), Error> {
+        let crate::Size(_w, h) = image.size();
+        let h = f32::from(h);
+
+        let width = self.scaled(self.theme.separator_width);
+        image.stroke(
+            self.theme.separator, /*color*/
+            &[PenWidth(width), Move(x, y), Line(x, h), Close()], /*path*/
+            buffer, /**/
+        )
+    }
+
+    // Get how far the pen advances when rendering `text` (without drawing).
+    fn advance(&self, text: &str) -> f32 {
+        let size = self.row_size() as f32 * 0.75;
+        text::measure(&self.font, size, text).advance
+    }
+
+    // Shorten `text` to fit `width` (see `truncate()`).
+    fn truncate(&self, text: &str, width: f32, ellipsis: Ellipsis) -> String {
+        let size = self.row_size() as f32 * 0.75;
+        text::truncate(&self.font, size, text, width, ellipsis)
+    }
+
+    // Draw the visible rows of a scrolling region between `xs.0` and `xs.1`.
+    fn rows<'b>(
         &mut self,
         image: &mut Image,
         buffer: &mut [u8],
+        region: Region,
+        xs: (f32, f32),
+        scroll: u32,
         generator: &Generator<'b>,
-    ) {
-        let crate::Size(w, _h) = image.size();
+    ) -> Result<(), Error> {
+        let crate::Size(w, h) = image.size();
+        self.wh = (f32::from(w), f32::from(h));
+        let h = u32::from(h);
+        let size = self.row_size();
+
+        // First visible row, and where it starts (may be above the region).
+        // A row that ends where the head does is hidden behind it.
+        let head = size + 1;
+        let mut row = (scroll / size) as usize;
+        let mut y = size as i32 - (scroll % size) as i32;
+        if y + size as i32 <= head as i32 {
+            row += 1;
+            y += size as i32;
+        }
+
+        // Forget rows from last time the region was drawn.
+        self.ydif_id[region as usize].clear();
+
+        // Keep rows from drawing over the head.
+        let head = head as f32;
+        image.push_clip_rect((xs.0, head, xs.1 - xs.0, h as f32 - head));
+
+        // Render From Generator until out of rows or out of space.
+        while y < h as i32 && xs.0 < xs.1 {
+            let widgets = match generator(row) {
+                Some(widgets) => widgets,
+                None => break,
+            };
+
+            let (top, height) = (y as f32, size as f32);
+            let area = (xs.0, top, xs.1 - xs.0, height);
+            let columns =
+                self.draw_row(image, buffer, region, area, &widgets)?;
+            self.ydif_id[region as usize].push(RowArea {
+                x: xs.0,
+                x_end: xs.1,
+                y: top,
+                y_end: top + height,
+                row,
+                columns,
+            });
+
+            row += 1;
+            y += size as i32;
+        }
+
+        // Fill the space below the last row.
+        if y < h as i32 && xs.0 < xs.1 {
+            let shape = rect_path(xs.0, y as f32, xs.1, h as f32);
+            image.fill(self.theme.background, &shape, buffer)?;
+        }
+        image.pop_clip();
+        self.draw_window_border(image, buffer)
+    }
+
+    // Lay out the columns of a row in `region` that is `width` wide (0 for
+    // unknown width).  Returns the x (relative to the row), width and content
+    // width of each column.
+    fn layout(
+        &self,
+        region: Region,
+        width: f32,
+        widgets: &[Widget],
+    ) -> Vec<(f32, f32, f32)> {
+        let size = self.row_size() as f32;
+        let pad = size * 0.125;
+        let rules = &self.columns[region as usize];
         let mut x = 0.0;
-        let y = 0.0; // Not mutable because we only call generator once.
-        let mut color = [0; 5];
-        let w = f32::from(w);
-        let size: f32 = self.size as f32;
-        let double_size = 2.0 * size;
 
-        // Render From Generator.
-        let iter = generator(0, &mut color);
-        let bg = [color[0], color[1], color[2], color[3]];
-        let fg = fgcolor_from_bg(bg);
+        widgets
+            .iter()
+            .enumerate()
+            .map(|(i, widget)| {
+                let content = match *widget {
+                    Widget::Text(text) => self.advance(text),
+                    Widget::Icon(icon) => self.scaled(extent(icon)),
+                    Widget::Button(label) => self.advance(label) + 2.0 * pad,
+                    Widget::Toggle(_) => size * 1.25,
+                    Widget::TextField(field) => {
+                        (self.advance(field.text()) + 2.0 * pad).max(size * 4.0)
+                    }
+                    Widget::Spacer(width) => self.scaled(width),
+                };
+
+                let rule = rules.get(i).cloned().unwrap_or_default();
+                let column = match rule.width {
+                    Width::Fixed(w) => self.scaled(w),
+                    Width::Fraction(f) if width > 0.0 => f * width,
+                    Width::Fit | Width::Fraction(_) => content + 2.0 * pad,
+                };
+                let start = x;
+                x += column;
+                (start, column, content)
+            })
+            .collect()
+    }
+
+    // Draw one row in `area` (x, y, width, height) of `region`.  Returns the
+    // start x of each column, followed by the end x of the last column.
+    fn draw_row(
+        &mut self,
+        image: &mut Image,
+        buffer: &mut [u8],
+        region: Region,
+        area: (f32, f32, f32, f32),
+        row: &Row,
+    ) -> Result<Vec<f32>, Error> {
+        let (x0, y, w, height) = area;
+        let pad = self.row_size() as f32 * 0.125;
+        let bg = row.background.unwrap_or(self.theme.background);
+        let fg = self.theme.foreground_on(bg);
+        let layout = self.layout(region, w, &row.widgets);
+        let rules = &self.columns[region as usize];
+        let mut columns = Vec::with_capacity(row.widgets.len() + 1);
+
+        // Keep widgets inside the row.
+        image.push_clip_rect(area);
 
         // Render Background.
-        let shape = [
-            Move(0.0, size),
-            Line(w, size),
-            Line(w, double_size),
-            Line(0.0, double_size),
-        ];
-        image.fill(bg /*color*/, &shape /*path*/, buffer /**/);
-        draw_window_border(image, buffer, (self.size * 2 - 1) as f32, color[4]);
-
-        // Render Slice
-        for (g, t) in iter {
+        let shape = rect_path(x0, y, x0 + w, y + height);
+        image.fill(bg /*color*/, &shape /*path*/, buffer /**/)?;
+        if row.separator {
+            let xs = (x0, x0 + w);
+            self.draw_separator(image, buffer, xs, y + height - 1.0)?;
+        }
+
+        // Render Widgets
+        for (i, (widget, (cx, cw, content))) in
+            row.widgets.iter().zip(layout.iter()).enumerate()
+        {
             // Iterate over columns in row.
-            for p in g.iter() {
-                // Iterate over paths in graphic.
-                image.fill(
-                    p.0, // color
-                    p.1, // path
-                    buffer,
-                );
+            let x = x0 + cx;
+            columns.push(x);
+
+            // Shorten text that doesn't fit in the column.
+            let rule = rules.get(i).cloned().unwrap_or_default();
+            let fit = cw - 2.0 * pad;
+            let short;
+            let (widget, content) = match *widget {
+                Widget::Text(text) if *content > fit => {
+                    short = self.truncate(text, fit, rule.ellipsis);
+                    (Widget::Text(&short), self.advance(&short))
+                }
+                Widget::Button(label) if *content > fit => {
+                    let fit = fit - 2.0 * pad;
+                    short = self.truncate(label, fit, rule.ellipsis);
+                    (Widget::Button(&short), self.advance(&short) + 2.0 * pad)
+                }
+                _ => (*widget, *content),
+            };
+
+            // Place the content within the column.
+            let x = match rule.align {
+                Align::Left => x + pad,
+                Align::Center => x + (cw - content) * 0.5,
+                Align::Right => x + cw - content - pad,
+            };
+
+            self.draw_widget(image, buffer, widget, (x, y, content), fg)?;
+        }
+        image.pop_clip();
+        columns.push(x0 + layout.last().map(|c| c.0 + c.1).unwrap_or(0.0));
+        Ok(columns)
+    }
+
+    // Draw a widget `xyw.2` wide at (`xyw.0`, `xyw.1`) with foreground color
+    // `fg`.
+    fn draw_widget(
+        &self,
+        image: &mut Image,
+        buffer: &mut [u8],
+        widget: Widget,
+        xyw: (f32, f32, f32),
+        fg: [u8; 4],
+    ) -> Result<(), Error> {
+        let (x, y, w) = xyw;
+        let size = self.row_size() as f32;
+        let pad = size * 0.125;
+        let text_xysize = (x, y + pad, size * 0.75);
+
+        match widget {
+            Widget::Text(text) => {
+                image.text(fg, text_xysize, &self.font, text, buffer)?;
+            }
+            Widget::Icon(icon) => {
+                // Paths in the icon are relative to the column.
+                image.save();
+                image.translate(x, y);
+                image.scale(self.scale, self.scale);
+                let drawn =
+                    icon.iter().try_for_each(|p| image.fill(p.0, p.1, buffer));
+                image.restore();
+                drawn?;
+            }
+            Widget::Button(label) => {
+                let rect = (x, y + pad * 0.5, w, size - pad);
+                let accent = self.theme.accent;
+                let radius = self.scaled(self.theme.radius);
+                image.fill(accent, &rounded(rect, radius, false), buffer)?;
+                let fg = self.theme.foreground_on(accent);
+                let text_xysize = (x + pad, text_xysize.1, text_xysize.2);
+                image.text(fg, text_xysize, &self.font, label, buffer)?;
+            }
+            Widget::Toggle(on) => {
+                let (h, r) = (size * 0.5, size * 0.2);
+                let track = (x, y + size * 0.25, w, h);
+                let knob_x = if on {
+                    x + w - h * 0.5 - r
+                } else {
+                    x + h * 0.5 - r
+                };
+                let knob = (knob_x, y + h - r, r * 2.0, r * 2.0);
+                let knob = rounded(knob, r, false);
+                if on {
+                    let accent = self.theme.accent;
+                    let track = rounded(track, h * 0.5, false);
+                    image.fill(accent, &track, buffer)?;
+                    let fg = self.theme.foreground_on(accent);
+                    image.fill(fg, &knob, buffer)?;
+                } else {
+                    let width = self.scale;
+                    image.fill(fg, &outline(track, h * 0.5, width), buffer)?;
+                    image.fill(fg, &knob, buffer)?;
+                }
             }
+            Widget::TextField(field) => {
+                let rect = (x, y + pad * 0.5, w, size - pad);
+                let radius = self.scaled(self.theme.radius);
+                image.fill(fg, &outline(rect, radius, self.scale), buffer)?;
 
-            // TODO: Increase X starting position for each column
-            x += image
-                .text(
+                // Draw the text before, in and after the selection, using the
+                // advance of each part to find where the next starts.
+                let text = field.text();
+                let selection = field.selection();
+                let (_, y, text_size) = text_xysize;
+                let (x, _) = image.text(
                     fg,
-                    (
-                        x + (self.size as f32 * 0.125),
-                        y + (self.size as f32 * 1.125),
-                        self.size as f32 * 0.75,
-                    ),
+                    (x + pad, y, text_size),
                     &self.font,
-                    t,
+                    &text[..selection.start],
                     buffer,
-                )
-                .0;
+                )?;
+                let (x_end, _) = image.text(
+                    fg,
+                    (x, y, text_size),
+                    &self.font,
+                    &text[selection.clone()],
+                    buffer,
+                )?;
+                image.text(
+                    fg,
+                    (x_end, y, text_size),
+                    &self.font,
+                    &text[selection.end..],
+                    buffer,
+                )?;
+
+                // Highlight the selection, and draw the caret.
+                let (top, bottom) = (y, y + text_size);
+                if !selection.is_empty() {
+                    let [r, g, b, _] = self.theme.accent;
+                    let shape = rect_path(x, top, x_end, bottom);
+                    image.fill([r, g, b, 96], &shape, buffer)?;
+                }
+                if field.is_focused() {
+                    let caret = if field.cursor() == selection.start {
+                        x
+                    } else {
+                        x_end
+                    };
+                    let caret_end = caret + self.scale;
+                    let shape = rect_path(caret, top, caret_end, bottom);
+                    image.fill(fg, &shape, buffer)?;
+                }
+            }
+            Widget::Spacer(_) => {}
         }
+        Ok(())
     }
 }
 
-// Create foreground color from background.
-fn fgcolor_from_bg(bg: [u8; 4]) -> [u8; 4] {
-    let mut brightness = 0;
-    for i in bg.iter() {
-        brightness += u32::from(*i);
+// Get a rectangle from (`x`, `y`) to (`x_end`, `y_end`).
+fn rect_path(x: f32, y: f32, x_end: f32, y_end: f32) -> [PathOp; 4] {
+    [Move(x, y), Line(x_end, y), Line(x_end, y_end), Line(x, y_end)]
+}
+
+// Get a rectangle (x, y, width, height) with corners rounded by radius `r`.
+//
+// Corners are made of line segments with points snapped to a quarter pixel,
+// which footile can always fill (curves and fractional points can produce
+// degenerate edges).  Points go clockwise, or counter-clockwise if `ccw`.
+fn rounded(rect: (f32, f32, f32, f32), r: f32, ccw: bool) -> Vec<PathOp> {
+    const SEGMENTS: usize = 4;
+
+    let (x, y, w, h) = rect;
+    let r = r.min(w * 0.5).min(h * 0.5).max(0.0);
+    let snap = |v: f32| (v * 4.0).round() / 4.0;
+    let corners = [
+        (x + w - r, y + r),
+        (x + w - r, y + h - r),
+        (x + r, y + h - r),
+        (x + r, y + r),
+    ];
+
+    let mut points = Vec::with_capacity(corners.len() * (SEGMENTS + 1));
+    for (i, (cx, cy)) in corners.iter().enumerate() {
+        for s in 0..=SEGMENTS {
+            let angle = (i as f32 - 1.0 + s as f32 / SEGMENTS as f32)
+                * std::f32::consts::FRAC_PI_2;
+            let (sin, cos) = angle.sin_cos();
+            points.push((snap(cx + r * cos), snap(cy + r * sin)));
+        }
     }
-    if brightness > 512 {
-        [0, 0, 0, 255]
-    } else {
-        [255, 255, 255, 255]
+    if ccw {
+        points.reverse();
     }
+
+    let mut path = Vec::with_capacity(points.len() + 1);
+    path.push(Move(points[0].0, points[0].1));
+    path.extend(points[1..].iter().map(|p| Line(p.0, p.1)));
+    path.push(Close());
+    path
 }
 
-// Draw the window border, and optional separator.
-fn draw_window_border(
-    image: &mut Image,
-    buffer: &mut [u8],
-    y: f32,
-    separator: u8,
-) {
-    let crate::Size(w, h) = image.size();
-    let w = f32::from(w);
-    let h = f32::from(h);
+// Get a `width` pixel wide outline of `rounded()`, as a path to fill.
+fn outline(rect: (f32, f32, f32, f32), r: f32, width: f32) -> Vec<PathOp> {
+    let (x, y, w, h) = rect;
+    let mut path = rounded(rect, r, false);
+    let (iw, ih) = ((w - 2.0 * width).max(0.0), (h - 2.0 * width).max(0.0));
+    path.extend(rounded((x + width, y + width, iw, ih), r - width, true));
+    path
+}
 
-    if separator == 0 {
-        image.stroke(
-            [0, 0, 0, 255], /*color*/
-            &[
-                PenWidth(2.0),
-                Move(0.0, 0.0),
-                Line(w, 0.0),
-                Line(w, h),
-                Line(0.0, h),
-                Line(0.0, 0.0),
-            ], /*path*/
-            buffer,         /**/
-        );
+// Get the width of a graphic (furthest right point of it's paths).
+fn extent(graphic: &[([u8; 4], &[PathOp])]) -> f32 {
+    let mut width = 0.0f32;
+    for (_, path) in graphic {
+        for op in path.iter() {
+            width = match *op {
+                Move(x, _) | Line(x, _) => width.max(x),
+                Quad(cx, _, x, _) => width.max(cx).max(x),
+                Cubic(ax, _, bx, _, x, _) => width.max(ax).max(bx).max(x),
+                Close() | PenWidth(_) => width,
+            };
+        }
+    }
+    width
+}
+
+// Get how far a `distance` (in pixels) is across the window `size`, 0 if the
+// window has no size yet (not drawn).
+fn amount(distance: f32, size: f32) -> f32 {
+    if size > 0.0 {
+        distance / size
     } else {
-        image.stroke(
-            [0, 0, 0, 255], /*color*/
-            &[
-                PenWidth(2.0),
-                Move(0.0, 0.0),
-                Line(w, 0.0),
-                Line(w, h),
-                Line(0.0, h),
-                Line(0.0, 0.0),
-                Close(),
-                PenWidth(0.5),
-                Move(0.0, y),
-                Line(w, y),
-                Close(),
-            ], /*path*/
-            buffer,         /**/
-        );
+        0.0
+    }
+}
+
+// Scroll a scroll position by `amount`, stopping at 0.
+fn scroll(position: &mut u32, amount: i32) {
+    if amount < 0 {
+        *position = position.saturating_sub(amount.unsigned_abs());
+    } else {
+        *position = position.saturating_add(amount as u32);
     }
 }
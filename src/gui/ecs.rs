@@ -1,47 +1,51 @@
 //! Extremely simple entity component system.
 
 /// Storage for a component in an entity component system.
-pub struct Storage<T: Clone, U: Into<u32>> {
+pub struct Storage<T: Default, U: Into<u32>> {
     // Vec element for each entity.
     entities: Vec<T>,
     _phantom_data: std::marker::PhantomData<U>,
 }
 
 impl<T, U> Storage<T, U>
-    where T: Clone, U: Into<u32>
+    where T: Default, U: Into<u32>
 {
-    /// Create a new storage with default value `none`.  `none` represents an entity not having this
-    /// component.
-    pub fn new(none: T) -> Storage<T, U> {
+    /// Create a new storage.  The default value of `T` represents an entity
+    /// not having this component.
+    pub fn new() -> Storage<T, U> {
         Storage {
-            entities: vec![none],
+            entities: vec![],
             _phantom_data: std::marker::PhantomData,
         }
     }
 
-    /// Get the value of the component for an entity.
+    /// Set the value of the component for an entity.
     pub fn set(&mut self, entity: U, value: T) {
         // Get Id as index.
-        let id = entity.into() as usize + 1;
+        let id = entity.into() as usize;
 
         // Allocate space if not enough.
         if id >= self.entities.len() {
-            self.entities.resize(id + 1, self.entities[0].clone() /* none */);
+            self.entities.resize_with(id + 1, T::default /* none */);
         }
         // Set entity's component.
         self.entities[id] = value;
     }
 
-    /// Get the value of the component for an entity.
-    pub fn get(&mut self, entity: U) -> T {
-        // Get Id as index.
-        let id = entity.into() as usize + 1;
+    /// Get the component for an entity, `None` if it was never set.
+    pub fn get_mut(&mut self, entity: U) -> Option<&mut T> {
+        self.entities.get_mut(entity.into() as usize)
+    }
 
-        // Get exists.
-        if let Some(rtn) = self.entities.get(id) {
-            rtn.clone()
-        } else {
-            self.entities[0].clone() /* none */
+    /// Remove the component from an entity.
+    pub fn remove(&mut self, entity: U) {
+        if let Some(value) = self.get_mut(entity) {
+            *value = T::default();
         }
     }
+
+    /// Find the first entity with a component value that matches `f`.
+    pub fn find<F: Fn(&T) -> bool>(&self, f: F) -> Option<u32> {
+        self.entities.iter().position(f).map(|id| id as u32)
+    }
 }
@@ -0,0 +1,187 @@
+//! Editable text for the GUI.
+
+use super::{Edit, Input};
+use std::ops::Range;
+
+/// The state of an editable text field, drawn with `Widget::TextField`.
+///
+/// The cursor and selection are byte indices into the text, always on `char`
+/// boundaries.  The selection is between the cursor and an anchor, which
+/// stays put while selecting.
+///
+/// ```
+/// use barg::{Edit, Input, TextField};
+///
+/// let mut field = TextField::new("Helo");
+/// field.input(Input::Edit(Edit::Left, false));
+/// field.input(Input::Text('l'));
+/// assert_eq!(field.text(), "Hello");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TextField {
+    // The text being edited.
+    text: String,
+    // Byte index of the cursor.
+    cursor: usize,
+    // Byte index of the other end of the selection (same as `cursor` for no
+    // selection).
+    anchor: usize,
+    // If the text field has keyboard focus (draws a caret).
+    focused: bool,
+}
+
+impl TextField {
+    /// Create a new text field, with the cursor at the end of `text`.
+    pub fn new(text: &str) -> Self {
+        TextField {
+            text: text.to_string(),
+            cursor: text.len(),
+            anchor: text.len(),
+            focused: false,
+        }
+    }
+
+    /// Get the text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, moving the cursor to the end.
+    pub fn set_text(&mut self, text: &str) {
+        *self = TextField {
+            focused: self.focused,
+            ..TextField::new(text)
+        };
+    }
+
+    /// Get the byte index of the cursor.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Get the byte range of the selected text (empty for no selection).
+    pub fn selection(&self) -> Range<usize> {
+        self.cursor.min(self.anchor)..self.cursor.max(self.anchor)
+    }
+
+    /// Select a byte range of the text, with the cursor at the end.  Indices
+    /// are clamped to the text, and moved back to a `char` boundary.
+    pub fn select(&mut self, range: Range<usize>) {
+        self.anchor = self.boundary(range.start);
+        self.cursor = self.boundary(range.end);
+    }
+
+    /// Give or take away keyboard focus.  Only focused text fields draw a
+    /// caret.
+    pub fn focus(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    /// Check if the text field has keyboard focus.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Send keyboard input to the text field.  Returns true if the text
+    /// changed.  Pointer input is ignored.
+    pub fn input(&mut self, input: Input) -> bool {
+        match input {
+            Input::Text(c) => {
+                self.delete_selection();
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+                self.anchor = self.cursor;
+                true
+            }
+            Input::Edit(Edit::Backspace, _) | Input::Edit(Edit::Delete, _)
+                if !self.selection().is_empty() =>
+            {
+                self.delete_selection();
+                true
+            }
+            Input::Edit(Edit::Backspace, _) => {
+                let start = self.prev(self.cursor);
+                self.text.replace_range(start..self.cursor, "");
+                let changed = start != self.cursor;
+                self.cursor = start;
+                self.anchor = start;
+                changed
+            }
+            Input::Edit(Edit::Delete, _) => {
+                let end = self.next(self.cursor);
+                self.text.replace_range(self.cursor..end, "");
+                end != self.cursor
+            }
+            Input::Edit(Edit::Left, select) => {
+                // Without selecting, collapse the selection to its start.
+                let selection = self.selection();
+                let index = if select || selection.is_empty() {
+                    self.prev(self.cursor)
+                } else {
+                    selection.start
+                };
+                self.move_cursor(index, select)
+            }
+            Input::Edit(Edit::Right, select) => {
+                // Without selecting, collapse the selection to its end.
+                let selection = self.selection();
+                let index = if select || selection.is_empty() {
+                    self.next(self.cursor)
+                } else {
+                    selection.end
+                };
+                self.move_cursor(index, select)
+            }
+            Input::Edit(Edit::Home, select) => self.move_cursor(0, select),
+            Input::Edit(Edit::End, select) => {
+                self.move_cursor(self.text.len(), select)
+            }
+            Input::Press(..) | Input::Drag(..) | Input::Release(..) => false,
+        }
+    }
+
+    // Move the cursor to `index`, moving the anchor with it if not
+    // `select`ing.  Returns false, because the text doesn't change.
+    fn move_cursor(&mut self, index: usize, select: bool) -> bool {
+        self.cursor = index;
+        if !select {
+            self.anchor = index;
+        }
+        false
+    }
+
+    // Remove the selected text, moving the cursor to where it was.
+    fn delete_selection(&mut self) {
+        let selection = self.selection();
+        self.cursor = selection.start;
+        self.anchor = selection.start;
+        self.text.replace_range(selection, "");
+    }
+
+    // Get the index of the `char` before `index`, or 0.
+    fn prev(&self, index: usize) -> usize {
+        self.text[..index]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    // Get the index after the `char` at `index`, or the end of the text.
+    fn next(&self, index: usize) -> usize {
+        self.text[index..]
+            .chars()
+            .next()
+            .map(|c| index + c.len_utf8())
+            .unwrap_or(index)
+    }
+
+    // Clamp `index` to the text, and move it back to a `char` boundary.
+    fn boundary(&self, index: usize) -> usize {
+        let mut index = index.min(self.text.len());
+        while !self.text.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    }
+}
@@ -0,0 +1,108 @@
+//! Colors and metrics for the GUI.
+
+/// Colors (sRGBA) and metrics (in pixels) used to draw a `Gui`.
+///
+/// ```
+/// use barg::Theme;
+///
+/// // A dark theme with a branded accent color.
+/// let theme = Theme {
+///     accent: [0xE0, 0x40, 0x80, 255],
+///     ..Theme::dark()
+/// };
+/// // Light text on black, and dark text on white.
+/// assert_eq!(theme.foreground_on([0, 0, 0, 255]), theme.foreground);
+/// assert_eq!(theme.foreground_on([255, 255, 255, 255]), theme.background);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Background of rows without their own background, and of empty space
+    /// below the last row.
+    pub background: [u8; 4],
+    /// Text and graphics drawn on the background.
+    pub foreground: [u8; 4],
+    /// Buttons, toggles that are on, and selected text.
+    pub accent: [u8; 4],
+    /// Outline of the window, popup and text fields.
+    pub border: [u8; 4],
+    /// Lines between rows, and between the sidebar and page.
+    pub separator: [u8; 4],
+    /// Width of the window and popup border.
+    pub border_width: f32,
+    /// Width of lines between rows, and between the sidebar and page.
+    pub separator_width: f32,
+    /// Corner radius of buttons and text fields.
+    pub radius: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::light()
+    }
+}
+
+impl Theme {
+    /// Dark text on a light background.
+    pub fn light() -> Self {
+        Theme {
+            background: [0xFF, 0xFF, 0xFF, 0xFF],
+            foreground: [0x00, 0x00, 0x00, 0xFF],
+            accent: [0x20, 0x60, 0xD0, 0xFF],
+            border: [0x00, 0x00, 0x00, 0xFF],
+            separator: [0x00, 0x00, 0x00, 0xFF],
+            border_width: 2.0,
+            separator_width: 1.0,
+            radius: 4.5,
+        }
+    }
+
+    /// Light text on a dark background.
+    pub fn dark() -> Self {
+        Theme {
+            background: [0x20, 0x20, 0x28, 0xFF],
+            foreground: [0xF0, 0xF0, 0xF0, 0xFF],
+            accent: [0x40, 0x80, 0xF0, 0xFF],
+            border: [0x00, 0x00, 0x00, 0xFF],
+            separator: [0x50, 0x50, 0x60, 0xFF],
+            ..Theme::light()
+        }
+    }
+
+    /// Get the color to draw text and graphics on `background`: whichever of
+    /// the theme's `foreground` and `background` has more contrast with it.
+    pub fn foreground_on(&self, background: [u8; 4]) -> [u8; 4] {
+        let fg = contrast(self.foreground, background);
+        let bg = contrast(self.background, background);
+
+        if fg >= bg {
+            self.foreground
+        } else {
+            self.background
+        }
+    }
+}
+
+/// Get the relative luminance of a color (sRGBA), from 0 for black to 1 for
+/// white.  Alpha is ignored.
+pub fn luminance(color: [u8; 4]) -> f32 {
+    let linear = |channel: u8| {
+        let c = f32::from(channel) / 255.0;
+        if c <= 0.039_28 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    0.2126 * linear(color[0])
+        + 0.7152 * linear(color[1])
+        + 0.0722 * linear(color[2])
+}
+
+/// Get the contrast ratio between two colors (sRGBA), from 1 for the same
+/// luminance to 21 for black and white.  Alpha is ignored.
+pub fn contrast(a: [u8; 4], b: [u8; 4]) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
@@ -0,0 +1,110 @@
+//! Rows of widgets for the GUI.
+
+use super::TextField;
+use fonterator::PathOp;
+
+/// A widget, which takes up one column of a `Row`.
+#[derive(Copy, Clone)]
+pub enum Widget<'b> {
+    /// A text label.
+    Text(&'b str),
+    /// An icon made of paths, each filled with a color (sRGBA).  Paths are
+    /// relative to the top left of the icon's column.
+    Icon(&'b [([u8; 4], &'b [PathOp])]),
+    /// A button with a text label.
+    Button(&'b str),
+    /// A toggle switch, which is either on (`true`) or off (`false`).
+    Toggle(bool),
+    /// An editable text field, with a caret if it has focus.
+    TextField(&'b TextField),
+    /// Empty space, some number of pixels wide.
+    Spacer(f32),
+}
+
+/// A row of widgets, returned from the generator passed to `Gui` regions.
+///
+/// ```
+/// use barg::Row;
+///
+/// let row = Row::new([255, 255, 255, 255])
+///     .separator()
+///     .text("Wi-Fi")
+///     .toggle(true);
+/// ```
+#[derive(Clone)]
+pub struct Row<'b> {
+    // Background color (sRGBA), or `None` for the theme's background.
+    pub(super) background: Option<[u8; 4]>,
+    // Whether or not to draw a separator at the bottom.
+    pub(super) separator: bool,
+    // Widgets, one for each column.
+    pub(super) widgets: Vec<Widget<'b>>,
+}
+
+impl<'b> Default for Row<'b> {
+    /// Create a new empty row with the `Theme`'s background color.
+    fn default() -> Self {
+        Row {
+            background: None,
+            separator: false,
+            widgets: vec![],
+        }
+    }
+}
+
+impl<'b> Row<'b> {
+    /// Create a new empty row with a background color (sRGBA).
+    pub fn new(background: [u8; 4]) -> Self {
+        Row {
+            background: Some(background),
+            ..Row::default()
+        }
+    }
+
+    /// Draw a separator at the bottom of the row.
+    pub fn separator(mut self) -> Self {
+        self.separator = true;
+        self
+    }
+
+    /// Add a widget to the end of the row.
+    pub fn widget(mut self, widget: Widget<'b>) -> Self {
+        self.widgets.push(widget);
+        self
+    }
+
+    /// Add a `Widget::Text` to the end of the row.
+    pub fn text(self, text: &'b str) -> Self {
+        self.widget(Widget::Text(text))
+    }
+
+    /// Add a `Widget::Icon` to the end of the row.
+    pub fn icon(self, icon: &'b [([u8; 4], &'b [PathOp])]) -> Self {
+        self.widget(Widget::Icon(icon))
+    }
+
+    /// Add a `Widget::Button` to the end of the row.
+    pub fn button(self, label: &'b str) -> Self {
+        self.widget(Widget::Button(label))
+    }
+
+    /// Add a `Widget::Toggle` to the end of the row.
+    pub fn toggle(self, on: bool) -> Self {
+        self.widget(Widget::Toggle(on))
+    }
+
+    /// Add a `Widget::TextField` to the end of the row.
+    pub fn text_field(self, field: &'b TextField) -> Self {
+        self.widget(Widget::TextField(field))
+    }
+
+    /// Add a `Widget::Spacer` to the end of the row.
+    pub fn spacer(self, width: f32) -> Self {
+        self.widget(Widget::Spacer(width))
+    }
+
+    /// Get the widgets in the row.
+    pub fn widgets(&self) -> &[Widget<'b>] {
+        &self.widgets
+    }
+}
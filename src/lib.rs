@@ -10,10 +10,38 @@ extern crate fonterator;
 use fonterator::footile;
 
 pub mod icons;
+mod cache;
+mod canvas;
+mod clip;
+mod error;
+#[cfg(feature = "png")]
+mod file;
+mod format;
+#[cfg(feature = "png")]
+mod golden;
 mod gui;
+mod paint;
+mod style;
+mod text;
+mod transform;
 mod window;
 
-pub use crate::gui::Gui;
+pub use crate::cache::GlyphCache;
+pub use crate::canvas::Canvas;
+pub use crate::error::Error;
+pub use crate::format::Format;
+#[cfg(feature = "png")]
+pub use crate::golden::Golden;
+pub use crate::gui::{
+    contrast, luminance, Align, Column, Edit, Gui, Hit, Id, Input, Region, Row,
+    Side, TextField, Theme, Widget, Width,
+};
+pub use crate::paint::{Paint, Pattern, Sampling, Spread};
+pub use crate::style::{Cap, FillRule, Join, StrokeStyle};
+pub use crate::text::{
+    measure, truncate, Ellipsis, Paragraph, Span, TextAlign, TextLayout,
+    TextMetrics,
+};
 pub use crate::window::*;
 
 pub use fonterator::{
@@ -22,132 +50,540 @@ pub use fonterator::{
     PathOp::{Line, Move, Quad},
 };
 
+use crate::clip::Clip;
 use footile::PixFmt;
 
 /// Size of an image (width, height).
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Size(pub u16, pub u16);
 
-/// Texture Coordinates (Mapped to a `PathOp`).
-#[derive(Copy, Clone)]
+/// Texture Coordinates (Mapped to a `PathOp`).  (0, 0) is the top left of
+/// the texture and (1, 1) is the bottom right.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct TexCoord(pub f32, pub f32);
 
 /// An Image
 pub struct Image {
     plotter: footile::Plotter,
     raster: footile::RasterB<footile::Rgba8>,
+    // Fill rule for `fill()`.
+    fill_rule: FillRule,
+    // Style for `stroke()`.
+    stroke_style: StrokeStyle,
+    // Last `PenWidth` given to the plotter (before transforming).
+    pen_width: f32,
+    // Affine matrix from path coordinates to pixels.
+    transform: [f32; 6],
+    // Transforms saved by `save()`.
+    saved: Vec<[f32; 6]>,
+    // Clips, each within the one before.
+    clips: Vec<Clip>,
+    // How the pixels are stored.
+    format: Format,
+    // Rasterized glyphs, for drawing text.
+    glyph_cache: Option<GlyphCache>,
+    // Which font text is drawn with, for the glyph cache.
+    font_id: u32,
 }
 
 impl Image {
-    /// Create new Image.
+    /// Create new Image, with sRGBA pixels.
     pub fn new(size: Size) -> Self {
+        Image::with_format(size, Format::Rgba8)
+    }
+
+    /// Create new Image, with pixels stored in `format`.
+    ///
+    /// ```
+    /// use barg::{Format, Image, Line, Move, Size};
+    ///
+    /// // A mask, with one byte per pixel.
+    /// let mut image = Image::with_format(Size(64, 64), Format::Gray8);
+    /// let mut buffer = vec![0; 64 * 64];
+    /// let triangle = [Move(0.0, 0.0), Line(64.0, 64.0), Line(0.0, 64.0)];
+    /// image.fill([255, 255, 255, 255], &triangle, &mut buffer).unwrap();
+    /// assert_eq!(buffer[63 * 64], 255);
+    /// assert_eq!(buffer[63], 0);
+    /// ```
+    pub fn with_format(size: Size, format: Format) -> Self {
         let (w, h) = (u32::from(size.0), u32::from(size.1));
 
         Image {
             plotter: footile::Plotter::new(w, h),
             raster: footile::RasterB::new(w, h),
+            fill_rule: FillRule::NonZero,
+            stroke_style: StrokeStyle::default(),
+            pen_width: 1.0,
+            transform: transform::IDENTITY,
+            saved: vec![],
+            clips: vec![],
+            format,
+            glyph_cache: None,
+            font_id: 0,
         }
     }
 
+    /// Save the transform, to go back to with `restore()`.
+    ///
+    /// ```
+    /// use barg::{Image, Line, Move, Size};
+    ///
+    /// let mut image = Image::new(Size(64, 64));
+    /// let mut buffer = vec![0; 64 * 64 * 4];
+    /// let square = [
+    ///     Move(0.0, 0.0),
+    ///     Line(1.0, 0.0),
+    ///     Line(1.0, 1.0),
+    ///     Line(0.0, 1.0),
+    /// ];
+    ///
+    /// // Draw the same square in 3 places, at 3 sizes.
+    /// for i in 1..=3 {
+    ///     image.save();
+    ///     image.translate(i as f32 * 16.0, 16.0);
+    ///     image.scale(i as f32 * 4.0, i as f32 * 4.0);
+    ///     image.fill([0, 0, 0, 255], &square, &mut buffer).unwrap();
+    ///     image.restore();
+    /// }
+    /// ```
+    pub fn save(&mut self) {
+        self.saved.push(self.transform);
+    }
+
+    /// Go back to the transform from the last `save()`.  Does nothing if
+    /// there's no saved transform.
+    pub fn restore(&mut self) {
+        if let Some(transform) = self.saved.pop() {
+            self.transform = transform;
+        }
+    }
+
+    /// Move paths drawn after this by (`x`, `y`).
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.transform([1.0, 0.0, 0.0, 1.0, x, y]);
+    }
+
+    /// Scale paths drawn after this by `x` horizontally and `y` vertically
+    /// (around (0, 0)).  Pen widths are scaled too.
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.transform([x, 0.0, 0.0, y, 0.0, 0.0]);
+    }
+
+    /// Rotate paths drawn after this clockwise by `angle` radians (around
+    /// (0, 0)).
+    pub fn rotate(&mut self, angle: f32) {
+        let (sin, cos) = angle.sin_cos();
+        self.transform([cos, sin, -sin, cos, 0.0, 0.0]);
+    }
+
+    /// Skew paths drawn after this by `x` radians horizontally and `y`
+    /// radians vertically.
+    pub fn skew(&mut self, x: f32, y: f32) {
+        self.transform([1.0, y.tan(), x.tan(), 1.0, 0.0, 0.0]);
+    }
+
+    /// Transform paths drawn after this with an affine `matrix`
+    /// [a, b, c, d, e, f], which maps (x, y) to
+    /// (a·x + c·y + e, b·x + d·y + f) before the current transform.
+    pub fn transform(&mut self, matrix: [f32; 6]) {
+        self.transform = transform::multiply(self.transform, matrix);
+    }
+
+    /// Replace the current transform with an affine `matrix` (see
+    /// `transform()`).
+    pub fn set_transform(&mut self, matrix: [f32; 6]) {
+        self.transform = matrix;
+    }
+
+    /// Only draw (and clear) inside a rectangle (x, y, width, height) and the
+    /// current clip, until `pop_clip()`.  The rectangle is transformed like
+    /// paths.
+    ///
+    /// ```
+    /// use barg::{Image, Line, Move, Size};
+    ///
+    /// let mut image = Image::new(Size(64, 64));
+    /// let mut buffer = vec![0; 64 * 64 * 4];
+    /// let square = [
+    ///     Move(0.0, 0.0),
+    ///     Line(64.0, 0.0),
+    ///     Line(64.0, 64.0),
+    ///     Line(0.0, 64.0),
+    /// ];
+    ///
+    /// // Only the top half gets filled.
+    /// image.push_clip_rect((0.0, 0.0, 64.0, 32.0));
+    /// image.fill([0, 0, 0, 255], &square, &mut buffer).unwrap();
+    /// image.pop_clip();
+    /// assert_eq!(buffer[(16 * 64) * 4 + 3], 255);
+    /// assert_eq!(buffer[(48 * 64) * 4 + 3], 0);
+    /// ```
+    pub fn push_clip_rect(&mut self, rect: (f32, f32, f32, f32)) {
+        let (x, y, w, h) = rect;
+        let m = self.transform;
+
+        // Rotated or skewed rectangles are clipped as paths.
+        if m[1] != 0.0 || m[2] != 0.0 {
+            let (x_end, y_end) = (x + w, y + h);
+            let path = [
+                Move(x, y),
+                Line(x_end, y),
+                Line(x_end, y_end),
+                Line(x, y_end),
+            ];
+            return self.push_clip_path(&path);
+        }
+
+        // Otherwise, keep it as a rectangle in pixels.
+        let (x0, y0) = transform::point(m, x, y);
+        let (x1, y1) = transform::point(m, x + w, y + h);
+        let (x0, x1) = (x0.min(x1), x0.max(x1));
+        let (y0, y1) = (y0.min(y1), y0.max(y1));
+        let width = self.raster.width() as usize;
+        let height = self.raster.height() as usize;
+        self.push_clip(Clip::rect((x0, y0, x1, y1), (width, height)));
+    }
+
+    /// Only draw (and clear) inside a path (with the fill rule) and the
+    /// current clip, until `pop_clip()`.
+    pub fn push_clip_path<'b, T>(&mut self, path: T)
+    where
+        T: IntoIterator<Item = &'b PathOp>,
+    {
+        let path = self.place(path);
+        let width = self.raster.width() as usize;
+        let mask = self.plotter.fill(&path, self.fill_rule.into());
+        let clip = Clip::mask(mask.pixels(), width);
+        self.plotter.clear_mask();
+        self.push_clip(clip);
+    }
+
+    /// Go back to the clip from before the last `push_clip_rect()` or
+    /// `push_clip_path()`.  Does nothing if there's no clip.
+    pub fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    /// Set the fill rule for `fill()` (`FillRule::NonZero` by default).
+    pub fn set_fill_rule(&mut self, rule: FillRule) {
+        self.fill_rule = rule;
+    }
+
+    /// Set how `stroke()` draws joins, caps and dashes.
+    pub fn set_stroke_style(&mut self, style: StrokeStyle) {
+        self.plotter.set_join(match style.join {
+            Join::Miter(limit) => footile::JoinStyle::Miter(limit),
+            Join::Round => footile::JoinStyle::Round,
+            Join::Bevel => footile::JoinStyle::Bevel,
+        });
+        self.stroke_style = style;
+    }
+
+    /// Set a cache of rasterized glyphs for drawing text, or `None` to
+    /// render text from paths every time (the default).  The cache is used
+    /// when the transform only moves and scales evenly (no rotating or
+    /// skewing), and text has no control characters.
+    pub fn set_glyph_cache(&mut self, cache: Option<GlyphCache>) {
+        self.glyph_cache = cache;
+    }
+
+    /// Set the id of the font that `text()` and `text_mask()` are given, so
+    /// the glyph cache can tell fonts apart (0 by default).  Give each font
+    /// its own id, and set it before drawing text with another font.
+    pub fn set_font_id(&mut self, id: u32) {
+        self.font_id = id;
+    }
+
     /// Get the size of the image.
     pub fn size(&self) -> Size {
         Size(self.raster.width() as u16, self.raster.height() as u16)
     }
 
+    /// Get how the pixels of the image are stored.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Get the glyph cache, if there is one.
+    pub fn glyph_cache(&self) -> Option<&GlyphCache> {
+        self.glyph_cache.as_ref()
+    }
+
     /// Clear the Image.
-    pub unsafe fn clear_ptr(&mut self, pixels: *mut u8) {
-        let len =
-            self.raster.width() as usize * self.raster.height() as usize * 4;
+    ///
+    /// # Safety
+    /// `pixels` must point to `width * height` pixels of the image's `Format`.
+    pub unsafe fn clear_ptr(&mut self, pixels: *mut u8) -> Result<(), Error> {
+        let len = self.len();
         self.clear(std::slice::from_raw_parts_mut(pixels, len))
     }
 
-    /// Clear the Image.
-    pub fn clear(&mut self, pixels: &mut [u8]) {
-        self.raster.clear(footile::Rgba8::as_slice_mut(pixels));
+    /// Clear the Image.  Returns an error if `pixels` isn't
+    /// the size of the image.
+    pub fn clear(&mut self, pixels: &mut [u8]) -> Result<(), Error> {
+        self.check(pixels)?;
+        let clip = match self.clips.last() {
+            Some(clip) => clip,
+            None => {
+                // Every format is transparent (or black) when zeroed.
+                for byte in pixels.iter_mut() {
+                    *byte = 0;
+                }
+                return Ok(());
+            }
+        };
+
+        // Fade clipped pixels to transparent by how much they're covered.
+        let (x, y, w, _) = clip.area();
+        let width = self.raster.width() as usize;
+        let bpp = self.format.bytes_per_pixel();
+        for (i, c) in clip.coverage().iter().enumerate() {
+            let (px, py) = (x + i % w, y + i / w);
+            let pixel = &mut pixels[(py * width + px) * bpp..][..bpp];
+            let mut color = self.format.load(pixel);
+            // Only alpha fades, unless colors are multiplied by it.
+            let fade = if self.format.is_straight() { 3 } else { 0 };
+            for channel in color[fade..].iter_mut() {
+                let keep = u32::from(*channel) * (255 - u32::from(*c));
+                *channel = ((keep + 127) / 255) as u8;
+            }
+            self.format.store(color, pixel);
+        }
+        Ok(())
     }
 
-    /// Draw a path a solid color (sRGBA).
-    pub unsafe fn fill_ptr<'b, T>(&mut self, color: [u8; 4], path: T, pixels: *mut u8)
+    /// Draw a path with a color (sRGBA) or other `Paint`.
+    ///
+    /// # Safety
+    /// `pixels` must point to `width * height` pixels of the image's `Format`.
+    pub unsafe fn fill_ptr<'b, 'p, T, P>(
+        &mut self,
+        paint: P,
+        path: T,
+        pixels: *mut u8,
+    ) -> Result<(), Error>
     where
         T: IntoIterator<Item = &'b PathOp>,
+        P: Into<Paint<'p>>,
     {
-        let len =
-            self.raster.width() as usize * self.raster.height() as usize * 4;
-        self.fill(color, path,
+        let len = self.len();
+        self.fill(paint, path,
             std::slice::from_raw_parts_mut(pixels, len)
         )
     }
 
-    /// Draw a path a solid color (sRGBA).
-    pub unsafe fn stroke_ptr<'b, T>(
+    /// Stroke a path with a color (sRGBA) or other `Paint`.
+    ///
+    /// # Safety
+    /// `pixels` must point to `width * height` pixels of the image's `Format`.
+    pub unsafe fn stroke_ptr<'b, 'p, T, P>(
         &mut self,
-        color: [u8; 4],
+        paint: P,
         path: T,
         pixels: *mut u8,
-    ) where
+    ) -> Result<(), Error>
+    where
         T: IntoIterator<Item = &'b PathOp>,
+        P: Into<Paint<'p>>,
     {
-        let len =
-            self.raster.width() as usize * self.raster.height() as usize * 4;
-        self.stroke(color, path,            std::slice::from_raw_parts_mut(pixels, len))
+        let len = self.len();
+        self.stroke(paint, path,            std::slice::from_raw_parts_mut(pixels, len))
     }
 
-    /// Draw a path a solid color (sRGBA).
-    pub fn fill<'b, T>(&mut self, color: [u8; 4], path: T, pixels: &mut [u8])
+    /// Draw a path with a color (sRGBA) or other `Paint`.  Returns an error
+    /// if `pixels` isn't the size of the image.
+    pub fn fill<'b, 'p, T, P>(
+        &mut self,
+        paint: P,
+        path: T,
+        pixels: &mut [u8],
+    ) -> Result<(), Error>
     where
         T: IntoIterator<Item = &'b PathOp>,
+        P: Into<Paint<'p>>,
     {
-        let iter = path.into_iter();
-        let color = footile::Rgba8::new(color[0], color[1], color[2], color[3]);
+        self.check(pixels)?;
+        let path = self.place(path);
 
-        self.raster.over(
-            self.plotter.fill(iter, footile::FillRule::NonZero),
-            color,
-            footile::Rgba8::as_slice_mut(pixels),
-        );
+        self.plotter.fill(&path, self.fill_rule.into());
+        self.over(paint.into(), pixels);
+        Ok(())
     }
 
-    /// Draw a path a solid color (sRGBA).
-    pub fn stroke<'b, T>(&mut self, color: [u8; 4], path: T, pixels: &mut [u8])
+    /// Stroke a path with a color (sRGBA) or other `Paint`.  Returns an
+    /// error if `pixels` isn't the size of the image.
+    pub fn stroke<'b, 'p, T, P>(
+        &mut self,
+        paint: P,
+        path: T,
+        pixels: &mut [u8],
+    ) -> Result<(), Error>
     where
         T: IntoIterator<Item = &'b PathOp>,
+        P: Into<Paint<'p>>,
     {
-        let iter = path.into_iter();
-        let color = footile::Rgba8::new(color[0], color[1], color[2], color[3]);
+        self.check(pixels)?;
 
-        self.raster.over(
-            self.plotter.stroke(iter),
-            color,
-            footile::Rgba8::as_slice_mut(pixels),
-        );
+        // Footile only does joins, so dashes and caps are added here.
+        if self.stroke_style.is_plain() {
+            let path = self.place(path);
+            self.plotter.stroke(&path);
+            self.over(paint.into(), pixels);
+            return Ok(());
+        }
+        let (stroke, caps, width) =
+            self.stroke_style.apply(path, self.pen_width);
+        let (stroke, caps) = (self.place(&stroke), self.place(&caps));
+        self.pen_width = width;
+        self.plotter.stroke(&stroke);
+        if caps.is_empty() {
+            self.over(paint.into(), pixels);
+            return Ok(());
+        }
+
+        // Filling overwrites the mask, so merge round caps in separately.
+        let (mut coverage, area) = self.clip_mask();
+        self.plotter.fill(&caps, footile::FillRule::NonZero);
+        let (caps, _) = self.clip_mask();
+        for (c, m) in coverage.iter_mut().zip(caps) {
+            *c = (*c).max(m);
+        }
+        let width = self.raster.width() as usize;
+        let inverse = self.inverse();
+        let format = self.format;
+        blend(paint.into(), inverse, &coverage, area, width, format, pixels);
+        Ok(())
+    }
+
+    /// Draw a path with a texture, mapped with `TexCoord`s.
+    ///
+    /// # Safety
+    /// `pixels` must point to `width * height` pixels of the image's `Format`.
+    pub unsafe fn fill_textured_ptr<'b, T>(
+        &mut self,
+        texture: Pattern,
+        path: T,
+        texcoords: &[TexCoord],
+        pixels: *mut u8,
+    ) -> Result<(), Error>
+    where
+        T: IntoIterator<Item = &'b PathOp>,
+    {
+        let len = self.len();
+        self.fill_textured(texture, path, texcoords,
+            std::slice::from_raw_parts_mut(pixels, len)
+        )
+    }
+
+    /// Draw a path with a texture, mapped with `TexCoord`s.  There is one
+    /// `TexCoord` for the end point of each `Move`, `Line`, `Quad` and
+    /// `Cubic` in the path.  The texture is mapped with the first 3 points
+    /// that aren't in a line (so it's exact for triangles, rectangles and
+    /// other parallelograms), replacing the transform of the `Pattern`.
+    /// Returns an error if `pixels` isn't the size of the image, if the
+    /// number of `texcoords` isn't the number of points
+    /// (`Error::TexCoords`), or if every point is in a line
+    /// (`Error::DegenerateTexCoords`).
+    pub fn fill_textured<'b, T>(
+        &mut self,
+        texture: Pattern,
+        path: T,
+        texcoords: &[TexCoord],
+        pixels: &mut [u8],
+    ) -> Result<(), Error>
+    where
+        T: IntoIterator<Item = &'b PathOp>,
+    {
+        let path: Vec<&PathOp> = path.into_iter().collect();
+        let points: Vec<(f32, f32)> = path
+            .iter()
+            .filter_map(|op| match **op {
+                Move(x, y) | Line(x, y) | Quad(_, _, x, y) => Some((x, y)),
+                Cubic(_, _, _, _, x, y) => Some((x, y)),
+                Close() | PenWidth(_) => None,
+            })
+            .collect();
+        if points.len() != texcoords.len() {
+            return Err(Error::TexCoords {
+                points: points.len(),
+                texcoords: texcoords.len(),
+            });
+        }
+        let Size(w, h) = texture.size();
+        let (w, h) = (f32::from(w), f32::from(h));
+        let pairs: Vec<((f32, f32), (f32, f32))> = points
+            .into_iter()
+            .zip(texcoords.iter().map(|t| (t.0 * w, t.1 * h)))
+            .collect();
+
+        // Find an affine matrix from the image to the texture.
+        let inverse = pairs.iter().enumerate().find_map(|(i, a)| {
+            pairs[i + 1..].iter().find_map(|b| {
+                let (p0, t0) = pairs[0];
+                let (p1, t1) = *a;
+                let (p2, t2) = *b;
+                #[rustfmt::skip]
+                let image = [
+                    p1.0 - p0.0, p1.1 - p0.1,
+                    p2.0 - p0.0, p2.1 - p0.1,
+                    p0.0, p0.1,
+                ];
+                #[rustfmt::skip]
+                let texture = [
+                    t1.0 - t0.0, t1.1 - t0.1,
+                    t2.0 - t0.0, t2.1 - t0.1,
+                    t0.0, t0.1,
+                ];
+                transform::invert(image)
+                    .map(|i| transform::multiply(texture, i))
+            })
+        });
+
+        let inverse = inverse.ok_or(Error::DegenerateTexCoords)?;
+        self.fill(texture.inverse(inverse), path, pixels)
     }
 
-    /// Draw text.
-    pub unsafe fn text_ptr(
+    /// Draw text with a color (sRGBA) or other `Paint`.
+    ///
+    /// # Safety
+    /// `pixels` must point to `width * height` pixels of the image's `Format`.
+    pub unsafe fn text_ptr<'p, P: Into<Paint<'p>>>(
         &mut self,
-        color: [u8; 4],
+        paint: P,
         xysize: (f32, f32, f32),
         font: &FontGroup,
         text: &str,
         pixels: *mut u8,
-    ) -> (f32, f32) {
-        let len =
-            self.raster.width() as usize * self.raster.height() as usize * 4;
-        self.text(color, xysize, font, text,
+    ) -> Result<(f32, f32), Error> {
+        let len = self.len();
+        self.text(paint, xysize, font, text,
             std::slice::from_raw_parts_mut(pixels, len)
         )
     }
 
-    /// Draw text.
-    pub fn text(
+    /// Draw text with a color (sRGBA) or other `Paint`.  Returns where the
+    /// pen ends up, or an error if `pixels` isn't the size of the image.
+    pub fn text<'p, P: Into<Paint<'p>>>(
         &mut self,
-        color: [u8; 4],
+        paint: P,
         xysize: (f32, f32, f32),
         font: &FontGroup,
         text: &str,
         pixels: &mut [u8],
-    ) -> (f32, f32) {
-        let color = footile::Rgba8::new(color[0], color[1], color[2], color[3]);
+    ) -> Result<(f32, f32), Error> {
+        self.check(pixels)?;
+
+        // Composite cached glyphs
+        if let Some((coverage, area, pen)) =
+            self.cached_text(xysize, font, text)
+        {
+            let width = self.raster.width() as usize;
+            let inverse = self.inverse();
+            let format = self.format;
+            let paint = paint.into();
+            blend(paint, inverse, &coverage, area, width, format, pixels);
+            return Ok(pen);
+        }
 
         // Render the text
         let mut path = font.render(
@@ -156,24 +592,1338 @@ impl Image {
             (xysize.2, xysize.2), /*size*/
         );
 
-        self.raster.over(
-            self.plotter.fill(&mut path, footile::FillRule::NonZero),
-            color,
-            footile::Rgba8::as_slice_mut(pixels),
-        );
+        let glyphs = self.place(&mut path);
+        self.plotter.fill(&glyphs, footile::FillRule::NonZero);
+        self.over(paint.into(), pixels);
 
         let (cx, cy) = path.xy();
 
-        (cx, cy)
+        Ok((cx, cy))
+    }
+
+    /// Draw a `Paragraph` (from `TextLayout::lay_out()`) with a color (sRGBA)
+    /// or other `Paint` and `font`, with its top left at (`x`, `y`).  `Span`s
+    /// with their own paint or font use those instead.  Returns an error
+    /// if `pixels` isn't the size of the image.
+    pub fn paragraph<'p, P: Into<Paint<'p>>>(
+        &mut self,
+        paint: P,
+        xy: (f32, f32),
+        font: &FontGroup,
+        paragraph: &Paragraph,
+        pixels: &mut [u8],
+    ) -> Result<(), Error> {
+        self.check(pixels)?;
+        let paint = paint.into();
+
+        for run in paragraph.runs() {
+            let (paint, size, font) = paragraph.style(run, paint, font);
+            let xysize = (xy.0 + run.x, xy.1 + run.y, size);
+            self.text(paint, xysize, font, &run.text, pixels)?;
+
+            for (x, y, w, h) in paragraph.decorations(run) {
+                let (x, y) = (xy.0 + x, xy.1 + y);
+                let line = [
+                    Move(x, y),
+                    Line(x + w, y),
+                    Line(x + w, y + h),
+                    Line(x, y + h),
+                ];
+                self.fill(paint, &line, pixels)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Render a path into `mask` (coverage from 0 to 255, one byte per
+    /// pixel) instead of drawing it, with the fill rule, transform and clip.
+    /// Coverage is added to what's already in the mask (keeping the larger
+    /// of the two), so shapes can be combined.  Returns an error if `mask`
+    /// isn't `width * height` bytes.
+    ///
+    /// ```
+    /// use barg::{Image, Line, Move, Size};
+    ///
+    /// let mut image = Image::new(Size(64, 64));
+    /// let mut mask = vec![0; 64 * 64];
+    /// let mut buffer = vec![0; 64 * 64 * 4];
+    /// let triangle = [Move(0.0, 0.0), Line(64.0, 64.0), Line(0.0, 64.0)];
+    ///
+    /// // Render once, then draw in any color.
+    /// image.fill_mask(&triangle, &mut mask).unwrap();
+    /// image.draw_mask([255, 0, 0, 255], &mask, &mut buffer).unwrap();
+    /// assert_eq!(mask[63 * 64], 255);
+    /// assert_eq!(&buffer[63 * 64 * 4..][..4], &[255, 0, 0, 255]);
+    /// ```
+    pub fn fill_mask<'b, T>(
+        &mut self,
+        path: T,
+        mask: &mut [u8],
+    ) -> Result<(), Error>
+    where
+        T: IntoIterator<Item = &'b PathOp>,
+    {
+        self.check_mask(mask)?;
+        let path = self.place(path);
+
+        self.plotter.fill(&path, self.fill_rule.into());
+        self.add_mask(mask);
+        Ok(())
+    }
+
+    /// Render text into `mask` (see `fill_mask()`) instead of drawing it.
+    /// Returns where the pen ends up, or an error if `mask` isn't
+    /// `width * height` bytes.
+    pub fn text_mask(
+        &mut self,
+        xysize: (f32, f32, f32),
+        font: &FontGroup,
+        text: &str,
+        mask: &mut [u8],
+    ) -> Result<(f32, f32), Error> {
+        self.check_mask(mask)?;
+
+        if let Some((coverage, area, pen)) =
+            self.cached_text(xysize, font, text)
+        {
+            let width = self.raster.width() as usize;
+            add(&coverage, area, width, mask);
+            return Ok(pen);
+        }
+
+        let mut path = font.render(
+            text,                 /*text*/
+            (xysize.0, xysize.1), /*position*/
+            (xysize.2, xysize.2), /*size*/
+        );
+
+        let glyphs = self.place(&mut path);
+        self.plotter.fill(&glyphs, footile::FillRule::NonZero);
+        self.add_mask(mask);
+
+        Ok(path.xy())
+    }
+
+    /// Draw a `mask` (from `fill_mask()` or `text_mask()`) with a color
+    /// (sRGBA) or other `Paint`, within the clip.  Returns an error if
+    /// `mask` isn't `width * height` bytes, or `pixels` isn't the size of
+    /// the image.
+    pub fn draw_mask<'p, P: Into<Paint<'p>>>(
+        &mut self,
+        paint: P,
+        mask: &[u8],
+        pixels: &mut [u8],
+    ) -> Result<(), Error> {
+        self.check_mask(mask)?;
+        self.check(pixels)?;
+
+        let width = self.raster.width() as usize;
+        let (coverage, area) =
+            crop(mask, width, self.drawable(), self.clips.last());
+        let inverse = self.inverse();
+        let format = self.format;
+        blend(paint.into(), inverse, &coverage, area, width, format, pixels);
+        Ok(())
+    }
+
+    // Get the number of bytes of pixels in the image.
+    fn len(&self) -> usize {
+        let (w, h) = (self.raster.width(), self.raster.height());
+        w as usize * h as usize * self.format.bytes_per_pixel()
+    }
+
+    // Check that `pixels` is the size of the image.
+    fn check(&self, pixels: &[u8]) -> Result<(), Error> {
+        if pixels.len() == self.len() {
+            Ok(())
+        } else {
+            Err(Error::BufferSize {
+                expected: self.len(),
+                actual: pixels.len(),
+            })
+        }
+    }
+
+    // Check that `mask` has a byte for each pixel of the image.
+    fn check_mask(&self, mask: &[u8]) -> Result<(), Error> {
+        let (w, h) = (self.raster.width(), self.raster.height());
+        let expected = w as usize * h as usize;
+        if mask.len() == expected {
+            Ok(())
+        } else {
+            Err(Error::BufferSize {
+                expected,
+                actual: mask.len(),
+            })
+        }
+    }
+
+    // Transform a path to pixels, keeping track of the pen width.  The path
+    // starts with the pen width, since it's scaled by the transform.
+    fn place<'b, T>(&mut self, path: T) -> Vec<PathOp>
+    where
+        T: IntoIterator<Item = &'b PathOp>,
+    {
+        let matrix = self.transform;
+        let mut ops = vec![PenWidth(self.pen_width * transform::scale(matrix))];
+        for op in path {
+            if let PenWidth(w) = *op {
+                self.pen_width = w;
+            }
+            ops.push(transform::path_op(matrix, op));
+        }
+        ops
+    }
+
+    // Get the affine matrix from pixels to path coordinates (where paints
+    // are placed).
+    fn inverse(&self) -> [f32; 6] {
+        transform::invert(self.transform).unwrap_or([0.0; 6])
+    }
+
+    // Composite the plotter's mask onto the pixels with a paint, and clear
+    // the mask.
+    fn over(&mut self, paint: Paint, pixels: &mut [u8]) {
+        let width = self.raster.width() as usize;
+        let inverse = self.inverse();
+        let format = self.format;
+
+        match (self.clips.last(), paint) {
+            // Opaque colors can use footile's (faster) compositing, if the
+            // pixels are in its (premultiplied) format.
+            (None, Paint::Solid([r, g, b, 255]))
+                if format == Format::Rgba8Premultiplied =>
+            {
+                self.raster.over(
+                    self.plotter.mask(),
+                    footile::Rgba8::new(r, g, b, 255),
+                    footile::Rgba8::as_slice_mut(pixels),
+                );
+                return;
+            }
+            (None, _) => {
+                let coverage = self.plotter.mask().pixels();
+                let area = (0, 0, width);
+                blend(paint, inverse, coverage, area, width, format, pixels);
+            }
+            (Some(_), _) => {
+                let (coverage, area) = self.clip_mask();
+                blend(paint, inverse, &coverage, area, width, format, pixels);
+                return;
+            }
+        }
+        self.plotter.clear_mask();
+    }
+
+    // Add the plotter's mask (within the clip) to `mask`, and clear it.
+    fn add_mask(&mut self, mask: &mut [u8]) {
+        let (coverage, area) = self.clip_mask();
+        let width = self.raster.width() as usize;
+        add(&coverage, area, width, mask);
+    }
+
+    // Get the area (x, y, width, height) of the image within the clip.
+    fn drawable(&self) -> (usize, usize, usize, usize) {
+        match self.clips.last() {
+            Some(clip) => clip.area(),
+            None => {
+                let width = self.raster.width() as usize;
+                (0, 0, width, self.raster.height() as usize)
+            }
+        }
+    }
+
+    // Copy the plotter's mask within the clip (scaled by the clip), and
+    // clear it.  Returns the coverage with its area (x, y, width).
+    fn clip_mask(&mut self) -> (Vec<u8>, Area) {
+        let width = self.raster.width() as usize;
+        let area = self.drawable();
+        let mask = self.plotter.mask().pixels();
+        let clipped = crop(mask, width, area, self.clips.last());
+        self.plotter.clear_mask();
+        clipped
+    }
+
+    // Rasterize text with the glyph cache, if there is one and the transform
+    // only moves and scales evenly.  Returns the coverage (within the image
+    // and clip), its area (x, y, width) of the image, and where the pen ends
+    // up.
+    fn cached_text(
+        &mut self,
+        xysize: (f32, f32, f32),
+        font: &FontGroup,
+        text: &str,
+    ) -> Option<(Vec<u8>, Area, (f32, f32))> {
+        let m = self.transform;
+        if m[1] != 0.0 || m[2] != 0.0 || m[0] != m[3] || m[0] <= 0.0 {
+            return None;
+        }
+        if text.chars().any(char::is_control) {
+            return None;
+        }
+        let cache = self.glyph_cache.as_mut()?;
+        let xy = transform::point(m, xysize.0, xysize.1);
+        let (mask, (x, y, w, h), end) =
+            cache.text(font, self.font_id, xysize.2 * m[0], xy, text);
+        let pen = ((end - m[4]) / m[0], xysize.1);
+
+        // Crop to the clip (or the image).
+        let (cx, cy, cw, ch) = self.drawable();
+        let (x0, y0) = (x.max(cx as i32) as usize, y.max(cy as i32) as usize);
+        let x1 = (x + w as i32).max(0).min((cx + cw) as i32) as usize;
+        let y1 = (y + h as i32).max(0).min((cy + ch) as i32) as usize;
+        if x1 <= x0 || y1 <= y0 {
+            return Some((vec![], (0, 0, 1), pen));
+        }
+        let cw = x1 - x0;
+        let mut coverage = Vec::with_capacity(cw * (y1 - y0));
+        let dx = (x0 as i32 - x) as usize;
+        for row in y0..y1 {
+            let start = (row as i32 - y) as usize * w + dx;
+            coverage.extend_from_slice(&mask[start..start + cw]);
+        }
+
+        if let Some(clip) = self.clips.last() {
+            clip.apply(&mut coverage, (x0, y0, cw));
+        }
+        Some((coverage, (x0, y0, cw), pen))
+    }
+
+    // Push a clip, within the current clip.
+    fn push_clip(&mut self, clip: Clip) {
+        let clip = match self.clips.last() {
+            Some(current) => clip.within(current),
+            None => clip,
+        };
+        self.clips.push(clip);
+    }
+}
+
+// An area (x, y, width) of an image, in pixels.
+type Area = (usize, usize, usize);
+
+// Copy the coverage (0 to 255) of `mask` (a byte for each pixel of an image
+// `width` pixels wide) within an `area` (x, y, width, height), scaled by the
+// `clip`.  Returns it with its area (x, y, width).
+fn crop(
+    mask: &[u8],
+    width: usize,
+    (x, y, w, h): (usize, usize, usize, usize),
+    clip: Option<&Clip>,
+) -> (Vec<u8>, Area) {
+    let mut coverage = Vec::with_capacity(w * h);
+    for row in y..y + h {
+        coverage.extend_from_slice(&mask[row * width + x..][..w]);
+    }
+    if let Some(clip) = clip {
+        clip.apply(&mut coverage, (x, y, w));
+    }
+    (coverage, (x, y, w))
+}
+
+// Add `coverage` (0 to 255) for each pixel of an `area` (x, y, width) of an
+// image `width` pixels wide to `mask`, keeping the most coverage.
+fn add(coverage: &[u8], (x0, y0, w): Area, width: usize, mask: &mut [u8]) {
+    for (i, c) in coverage.iter().enumerate() {
+        let m = &mut mask[(y0 + i / w) * width + x0 + i % w];
+        *m = (*m).max(*c);
+    }
+}
+
+// Composite a paint (placed with `inverse`, from pixels to path coordinates)
+// onto pixels in `format` of an image `width` pixels wide, with `coverage` (0
+// to 255) for each pixel of an `area` (x, y, width) of the image.
+fn blend(
+    paint: Paint,
+    inverse: [f32; 6],
+    coverage: &[u8],
+    (x0, y0, w): Area,
+    width: usize,
+    format: Format,
+    pixels: &mut [u8],
+) {
+    // Solid colors are the same everywhere, so they aren't placed for each
+    // pixel, and they're converted to the format once, to copy onto fully
+    // covered pixels when opaque.  This keeps the common case (like `Gui`
+    // rows) to integer math and copies.
+    let bpp = format.bytes_per_pixel();
+    let solid = match paint {
+        Paint::Solid(color) => Some(color),
+        _ => None,
+    };
+    let mut opaque = [0; 4];
+    if let Some(color) = solid {
+        format.store(color, &mut opaque[..bpp]);
+    }
+    if w == 0 {
+        return;
+    }
+
+    for (line, py) in coverage.chunks_exact(w).zip(y0..) {
+        let start = (py * width + x0) * bpp;
+        let row = pixels[start..][..w * bpp].chunks_exact_mut(bpp);
+        for ((m, pixel), px) in line.iter().zip(row).zip(x0..) {
+            if *m == 0 {
+                continue;
+            }
+            let color = match solid {
+                Some(color) if *m == 255 && color[3] == 255 => {
+                    pixel.copy_from_slice(&opaque[..bpp]);
+                    continue;
+                }
+                Some(color) => color,
+                None => {
+                    let (x, y) = (px as f32 + 0.5, py as f32 + 0.5);
+                    let (x, y) = transform::point(inverse, x, y);
+                    paint.color_at(x, y)
+                }
+            };
+            // Scale the coverage by the alpha of the paint.
+            let a = (u32::from(*m) * u32::from(color[3]) + 127) / 255;
+            format.over(color, a, pixel);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn gradient() {
+        let mut image = Image::new(Size(8, 1));
+        let mut buffer = vec![0; 8 * 4];
+        let stops = [(0.0, [0, 0, 0, 255]), (1.0, [255, 255, 255, 255])];
+        let path =
+            [Move(0.0, 0.0), Line(8.0, 0.0), Line(8.0, 1.0), Line(0.0, 1.0)];
+        let red = |buffer: &[u8]| -> Vec<u8> {
+            buffer.chunks(4).map(|p| p[0]).collect()
+        };
+
+        // Black to white over the first half, then padded.
+        let (start, end) = ((0.0, 0.0), (4.0, 0.0));
+        let paint = Paint::Linear(start, end, &stops, Spread::Pad);
+        image.fill(paint, &path, &mut buffer).unwrap();
+        assert_eq!(red(&buffer), [32, 96, 159, 223, 255, 255, 255, 255]);
+
+        // Reflected back to black.
+        let paint = Paint::Linear(start, end, &stops, Spread::Reflect);
+        image.fill(paint, &path, &mut buffer).unwrap();
+        assert_eq!(red(&buffer), [32, 96, 159, 223, 223, 159, 96, 32]);
+    }
+
+    #[test]
+    fn textured() {
+        let mut image = Image::new(Size(4, 4));
+        let mut buffer = vec![0; 4 * 4 * 4];
+        #[rustfmt::skip]
+        let texture = [
+            10, 0, 0, 255, 20, 0, 0, 255,
+            30, 0, 0, 255, 40, 0, 0, 255,
+        ];
+        let texture = Pattern::new(&texture, Size(2, 2));
+        let path =
+            [Move(0.0, 0.0), Line(4.0, 0.0), Line(4.0, 4.0), Line(0.0, 4.0)];
+        let red = |buffer: &[u8]| -> Vec<u8> {
+            buffer.chunks(4).map(|p| p[0]).collect()
+        };
+
+        // Flipped horizontally, so the right column of the texture is on the
+        // left.
+        let texcoords = [
+            TexCoord(1.0, 0.0),
+            TexCoord(0.0, 0.0),
+            TexCoord(0.0, 1.0),
+            TexCoord(1.0, 1.0),
+        ];
+        let nearest = texture.sampling(Sampling::Nearest);
+        image.fill_textured(nearest, &path, &texcoords, &mut buffer).unwrap();
+        assert_eq!(red(&buffer)[..8], [20, 20, 10, 10, 20, 20, 10, 10]);
+
+        // There has to be a texture coordinate for each point.
+        let three = &texcoords[..3];
+        match image.fill_textured(nearest, &path, three, &mut buffer) {
+            Err(Error::TexCoords { points, texcoords }) => {
+                assert_eq!((points, texcoords), (4, 3));
+            }
+            other => panic!("expected an error, got {:?}", other),
+        }
+        // And 3 points that aren't in a line.
+        let line = [Move(0.0, 0.0), Line(2.0, 2.0), Line(4.0, 4.0)];
+        match image.fill_textured(nearest, &line, three, &mut buffer) {
+            Err(Error::DegenerateTexCoords) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+
+        // Stretched with bilinear sampling.
+        let stretched = texture.stretch((0.0, 0.0, 4.0, 4.0));
+        image.fill(stretched, &path, &mut buffer).unwrap();
+        assert_eq!(red(&buffer)[..4], [10, 13, 18, 20]);
+    }
+
+    #[test]
+    fn stroke_style() {
+        let mut image = Image::new(Size(16, 8));
+        let mut buffer = vec![0; 16 * 8 * 4];
+        let covered = |buffer: &[u8], x: usize, y: usize| {
+            buffer[(y * 16 + x) * 4 + 3] > 127
+        };
+
+        // A square with a hole in the middle.
+        image.set_fill_rule(FillRule::EvenOdd);
+        #[rustfmt::skip]
+        let path = [
+            Move(0.0, 0.0), Line(8.0, 0.0), Line(8.0, 8.0), Line(0.0, 8.0),
+            Move(2.0, 2.0), Line(6.0, 2.0), Line(6.0, 6.0), Line(2.0, 6.0),
+        ];
+        image.fill([0, 0, 0, 255], &path, &mut buffer).unwrap();
+        assert!(covered(&buffer, 1, 4));
+        assert!(!covered(&buffer, 4, 4));
+
+        // Dashes 2 on, 6 off, with caps extending them by 1 on each end.
+        image.set_stroke_style(StrokeStyle {
+            cap: Cap::Square,
+            dashes: vec![2.0, 6.0],
+            ..StrokeStyle::default()
+        });
+        let path = [PenWidth(2.0), Move(10.0, 4.5), Line(16.0, 4.5)];
+        image.stroke([0, 0, 0, 255], &path, &mut buffer).unwrap();
+        assert!(covered(&buffer, 9, 4));
+        assert!(covered(&buffer, 12, 4));
+        assert!(!covered(&buffer, 14, 4));
+
+        // Tiny dashes are made long enough to finish.
+        image.set_stroke_style(StrokeStyle {
+            dashes: vec![1e-30, 1e-30],
+            ..StrokeStyle::default()
+        });
+        let mut buffer = vec![0; 16 * 8 * 4];
+        let path = [PenWidth(2.0), Move(1.0, 4.5), Line(14.0, 4.5)];
+        image.stroke([0, 0, 0, 255], &path, &mut buffer).unwrap();
+        let alpha = buffer[(4 * 16 + 7) * 4 + 3];
+        assert!(alpha > 64 && alpha < 192, "{}", alpha);
+    }
+
+    #[test]
+    fn transform() {
+        let mut image = Image::new(Size(8, 8));
+        let mut buffer = vec![0; 8 * 8 * 4];
+        let covered = |buffer: &[u8], x: usize, y: usize| {
+            buffer[(y * 8 + x) * 4 + 3] > 127
+        };
+        let square =
+            [Move(0.0, 0.0), Line(1.0, 0.0), Line(1.0, 1.0), Line(0.0, 1.0)];
+
+        // Scaled to 4x2, then moved to (2, 6).
+        image.save();
+        image.translate(2.0, 6.0);
+        image.scale(4.0, 2.0);
+        image.fill([0, 0, 0, 255], &square, &mut buffer).unwrap();
+        image.restore();
+        assert!(covered(&buffer, 2, 6) && covered(&buffer, 5, 7));
+        assert!(!covered(&buffer, 1, 6) && !covered(&buffer, 6, 7));
+        assert!(!covered(&buffer, 2, 5));
+
+        // Restored, so back to pixels.
+        let column =
+            [Move(0.0, 0.0), Line(1.0, 0.0), Line(1.0, 8.0), Line(0.0, 8.0)];
+        image.fill([0, 0, 0, 255], &column, &mut buffer).unwrap();
+        assert!(covered(&buffer, 0, 3) && !covered(&buffer, 1, 3));
+
+        // Paints are transformed with the path.
+        let stops = [(0.0, [0, 0, 0, 255]), (1.0, [255, 255, 255, 255])];
+        let paint = Paint::Linear((0.0, 0.0), (1.0, 0.0), &stops, Spread::Pad);
+        image.set_transform([8.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+        image.fill(paint, &column, &mut buffer).unwrap();
+        let red: Vec<u8> = buffer[..8 * 4].chunks(4).map(|p| p[0]).collect();
+        assert_eq!(red, [16, 48, 80, 112, 143, 175, 207, 239]);
+    }
+
+    #[test]
+    fn clip() {
+        let mut image = Image::new(Size(8, 8));
+        let mut buffer = vec![255; 8 * 8 * 4];
+        let alpha =
+            |buffer: &[u8], x: usize, y: usize| buffer[(y * 8 + x) * 4 + 3];
+        let square =
+            [Move(0.0, 0.0), Line(8.0, 0.0), Line(8.0, 8.0), Line(0.0, 8.0)];
+
+        // Clear a hole.
+        image.push_clip_rect((2.0, 2.0, 4.0, 4.0));
+        image.clear(&mut buffer).unwrap();
+        assert_eq!((alpha(&buffer, 2, 2), alpha(&buffer, 1, 2)), (0, 255));
+
+        // Fill the left half of the hole.
+        image.push_clip_path(&[
+            Move(0.0, 0.0),
+            Line(4.0, 0.0),
+            Line(4.0, 8.0),
+            Line(0.0, 8.0),
+        ]);
+        image.fill([0, 0, 0, 255], &square, &mut buffer).unwrap();
+        assert_eq!((alpha(&buffer, 3, 3), alpha(&buffer, 4, 3)), (255, 0));
+        image.pop_clip();
+        image.pop_clip();
+
+        // Half a pixel wide.
+        image.clear(&mut buffer).unwrap();
+        image.push_clip_rect((0.0, 0.0, 0.5, 8.0));
+        image.fill([0, 0, 0, 255], &square, &mut buffer).unwrap();
+        assert_eq!((alpha(&buffer, 0, 3), alpha(&buffer, 1, 3)), (128, 0));
+        image.pop_clip();
+
+        // Rectangles within a rectangle.
+        image.clear(&mut buffer).unwrap();
+        image.push_clip_rect((2.0, 2.0, 2.0, 2.0));
+        image.push_clip_rect((3.0, 0.0, 5.0, 8.0));
+        image.fill([0, 0, 0, 255], &square, &mut buffer).unwrap();
+        assert_eq!((alpha(&buffer, 3, 3), alpha(&buffer, 2, 3)), (255, 0));
+        assert_eq!((alpha(&buffer, 4, 3), alpha(&buffer, 3, 1)), (0, 0));
+        image.pop_clip();
+        image.pop_clip();
+
+        // Rectangles within a triangle.
+        image.clear(&mut buffer).unwrap();
+        image.push_clip_path(&[Move(0.0, 0.0), Line(8.0, 0.0), Line(0.0, 8.0)]);
+        image.push_clip_rect((4.0, 0.0, 4.0, 8.0));
+        image.fill([0, 0, 0, 255], &square, &mut buffer).unwrap();
+        assert_eq!((alpha(&buffer, 5, 1), alpha(&buffer, 5, 4)), (255, 0));
+        assert_eq!(alpha(&buffer, 2, 1), 0);
+        image.push_clip_rect((0.0, 0.0, 6.0, 2.0));
+        let mut buffer = vec![255; 8 * 8 * 4];
+        image.clear(&mut buffer).unwrap();
+        assert_eq!((alpha(&buffer, 5, 1), alpha(&buffer, 6, 1)), (0, 255));
+        assert_eq!(alpha(&buffer, 5, 2), 255);
+        image.pop_clip();
+        image.pop_clip();
+        image.pop_clip();
+
+        // Scrolled rows don't draw over the head.
+        let mut gui = Gui::new(FontGroup::default());
+        let mut image = Image::new(Size(100, 100));
+        let mut buffer = vec![0; 100 * 100 * 4];
+        gui.scroll(15);
+        gui.page(&mut image, &mut buffer, &|row| {
+            Some(Row::default().text("Row")).filter(|_| row < 10)
+        }).unwrap();
+        assert_eq!(buffer[(30 * 100 + 50) * 4 + 3], 0);
+        assert_eq!(buffer[(50 * 100 + 50) * 4 + 3], 255);
+    }
+
+    #[test]
+    fn buffer_size() {
+        let mut image = Image::new(Size(4, 4));
+        let mut short = vec![0; 4 * 4 * 4 - 1];
+        let triangle = [Move(0.0, 0.0), Line(4.0, 0.0), Line(4.0, 4.0)];
+
+        match image.fill([0, 0, 0, 255], &triangle, &mut short) {
+            Err(Error::BufferSize {
+                expected: 64,
+                actual: 63,
+            }) => {}
+            other => panic!("expected a buffer size error, got {:?}", other),
+        }
+        assert!(image.clear(&mut short).is_err());
+        assert!(short.iter().all(|p| *p == 0));
+
+        // Canvases always have the right size.
+        assert!(Canvas::from_pixels(Size(4, 4), short).is_err());
+        let mut canvas = Canvas::from_pixels(Size(4, 4), vec![255; 64]).unwrap();
+        canvas.clear();
+        assert_eq!(canvas.into_pixels(), vec![0; 64]);
+    }
+
+    #[test]
+    fn formats() {
+        let square = [
+            Move(0.0, 0.0),
+            Line(4.0, 0.0),
+            Line(4.0, 4.0),
+            Line(0.0, 4.0),
+        ];
+        let first = |format, color: [u8; 4]| {
+            let mut canvas = Canvas::with_format(Size(4, 4), format);
+            canvas.fill(color, &square);
+            assert_eq!(canvas.pixels().len(), 16 * format.bytes_per_pixel());
+            canvas.pixels()[..format.bytes_per_pixel()].to_vec()
+        };
+        let close = |a: Vec<u8>, e: &[u8]| {
+            let off =
+                a.iter().zip(e).map(|(a, e)| (*a as i16 - *e as i16).abs());
+            assert!(off.max() <= Some(2), "{:?} isn't {:?}", a, e);
+        };
+        let orange = [255, 128, 0, 255];
+
+        close(first(Format::Rgba8, orange), &[255, 128, 0, 255]);
+        close(first(Format::Bgra8, orange), &[0, 128, 255, 255]);
+        close(first(Format::Gray8, orange), &[146]);
+        close(first(Format::Rgb565, orange), &[0x00, 0xFC]);
+        let half = [255, 0, 0, 128];
+        close(first(Format::Rgba8Premultiplied, half), &[128, 0, 0, 128]);
+
+        // Patterns in other formats read back the same colors.
+        let mut canvas = Canvas::with_format(Size(4, 4), Format::Bgra8);
+        canvas.fill(orange, &square);
+        let mut copy = Canvas::new(Size(4, 4));
+        copy.fill(canvas.pattern(), &square);
+        close(copy.pixels()[..4].to_vec(), &[255, 128, 0, 255]);
+    }
+
+    #[test]
+    fn translucent() {
+        let square = [
+            Move(0.0, 0.0),
+            Line(4.0, 0.0),
+            Line(4.0, 4.0),
+            Line(0.0, 4.0),
+        ];
+        let half = [255, 0, 0, 128];
+        let formats = [
+            (Format::Rgba8, [255, 0, 0, 128]),
+            (Format::Bgra8, [0, 0, 255, 128]),
+            (Format::Rgba8Premultiplied, [128, 0, 0, 128]),
+        ];
+
+        for (format, stored) in formats.iter() {
+            // Drawn on a transparent canvas, it's stored as is.
+            let mut canvas = Canvas::with_format(Size(4, 4), *format);
+            canvas.fill(half, &square);
+            assert_eq!(canvas.pixels()[..4], *stored, "{:?}", format);
+
+            // And reads back the same color.
+            let mut copy = Canvas::new(Size(4, 4));
+            copy.fill(canvas.pattern(), &square);
+            assert_eq!(copy.pixels()[..4], half, "{:?}", format);
+
+            // Drawn over itself, it's more opaque but still red.
+            canvas.fill(half, &square);
+            let pixel = format.rgba(&canvas.pixels()[..4]);
+            assert_eq!(pixel, [255, 0, 0, 192], "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn mask() {
+        let mut image = Image::new(Size(8, 8));
+        let mut mask = vec![0; 8 * 8];
+        let column = |x: f32| {
+            [Move(x, 0.0), Line(x + 2.0, 0.0), Line(x + 2.0, 8.0), Line(x, 8.0)]
+        };
+        let (left, right) = (column(0.0), column(6.0));
+
+        // Shapes are combined, and clipped.
+        image.fill_mask(&left, &mut mask).unwrap();
+        image.push_clip_rect((0.0, 0.0, 8.0, 4.0));
+        image.fill_mask(&right, &mut mask).unwrap();
+        image.pop_clip();
+        assert!(mask[7 * 8] > 127);
+        assert!(mask[3 * 8 + 7] > 127);
+        assert_eq!(mask[7 * 8 + 7], 0);
+        assert!(image.fill_mask(&left, &mut [0; 63]).is_err());
+
+        // Drawing the mask is the same as filling.
+        let mut filled = vec![0; 8 * 8 * 4];
+        let mut drawn = vec![0; 8 * 8 * 4];
+        image.fill([0, 0, 255, 128], &left, &mut filled).unwrap();
+        let mut mask = vec![0; 8 * 8];
+        image.fill_mask(&left, &mut mask).unwrap();
+        image.draw_mask([0, 0, 255, 128], &mask, &mut drawn).unwrap();
+        assert_eq!(filled, drawn);
+
+        // Text masks end at the same pen position as drawn text.
+        let font = FontGroup::default();
+        let mut mask = vec![0; 8 * 8];
+        let pen = image.text_mask((0.0, 0.0, 8.0), &font, "Hi", &mut mask);
+        let end = image.text([0; 4], (0.0, 0.0, 8.0), &font, "Hi", &mut drawn);
+        assert_eq!(pen.unwrap(), end.unwrap());
+        assert!(mask.iter().any(|m| *m != 0));
+    }
+
+    #[test]
+    fn paragraph() {
+        // The built-in font is monospace: 8 pixels per letter at size 16.
+        let font = FontGroup::default();
+        let lay_out = |align, width, text| {
+            let paragraph = TextLayout::new(16.0)
+                .width(width)
+                .align(align)
+                .lay_out(&font, text);
+            let runs: Vec<(String, f32, f32)> = paragraph
+                .runs()
+                .iter()
+                .map(|run| (run.text.clone(), run.x, run.y))
+                .collect();
+            (runs, paragraph.bbox())
+        };
+        let run = |text: &str, x, y| (text.to_string(), x, y);
+
+        let (runs, bbox) = lay_out(TextAlign::Left, 40.0, "aa  bb cc");
+        assert_eq!(runs, [run("aa bb", 0.0, 0.0), run("cc", 0.0, 20.0)]);
+        assert_eq!(bbox, (0.0, 0.0, 40.0, 40.0));
+        let (runs, bbox) = lay_out(TextAlign::Center, 40.0, "aa bb cc");
+        assert_eq!(runs[1], run("cc", 12.0, 20.0));
+        assert_eq!(bbox, (0.0, 0.0, 40.0, 40.0));
+        let (runs, _) = lay_out(TextAlign::Right, 40.0, "aa bb cc");
+        assert_eq!(runs[1], run("cc", 24.0, 20.0));
+
+        // The last line isn't justified.
+        let (runs, _) = lay_out(TextAlign::Justify, 60.0, "aa bb cc");
+        assert_eq!(
+            runs,
+            [run("aa", 0.0, 0.0), run("bb", 44.0, 0.0), run("cc", 0.0, 20.0)]
+        );
+
+        // Newlines (even blank lines) start new lines.
+        let (runs, bbox) = lay_out(TextAlign::Left, 100.0, "aa\n\nbb");
+        assert_eq!(runs, [run("aa", 0.0, 0.0), run("bb", 0.0, 40.0)]);
+        assert_eq!(bbox, (0.0, 0.0, 16.0, 60.0));
+    }
+
+    #[test]
+    fn spans() {
+        let font = FontGroup::default();
+        let spans = [
+            Span::new("aa "),
+            Span::new("bb").size(32.0),
+            Span::new(" cc dd").underline(true).strikethrough(true),
+        ];
+        let paragraph = TextLayout::new(16.0).lay_out_spans(&font, &spans);
+        let runs = paragraph.runs();
+
+        // Spans are laid out in one line, with words of a span together.
+        let texts: Vec<(&str, f32)> =
+            runs.iter().map(|run| (run.text.as_str(), run.x)).collect();
+        assert_eq!(texts, [("aa", 0.0), ("bb", 24.0), ("cc dd", 64.0)]);
+        assert_eq!(paragraph.bbox(), (0.0, 0.0, 104.0, 40.0));
+
+        // Text of different sizes shares the baseline.
+        let small = measure(&font, 16.0, "").ascent;
+        let big = measure(&font, 32.0, "").ascent;
+        assert_eq!(runs[0].y, big - small);
+        assert_eq!(runs[1].y, 0.0);
+        let lines = paragraph.decorations(&runs[2]);
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.0 == 64.0 && line.2 == 40.0));
+        assert!(lines[0].1 > big && lines[1].1 < big);
+    }
+
+    #[test]
+    fn measure_text() {
+        let font = FontGroup::default();
+        let empty = measure(&font, 16.0, " ");
+        assert_eq!(empty.advance, 8.0);
+        assert_eq!(empty.bbox, (0.0, 0.0, 0.0, 0.0));
+
+        // Descenders go below the baseline.
+        let metrics = measure(&font, 32.0, "Hg");
+        assert_eq!(metrics.advance, 32.0);
+        let (x, y, w, h) = metrics.bbox;
+        assert!(y + h > metrics.ascent);
+        assert!(y + h <= metrics.ascent + metrics.descent);
+
+        // The box is tight around what's drawn.
+        let mut canvas = Canvas::new(Size(40, 40));
+        canvas.text([0, 0, 0, 255], (0.0, 0.0, 32.0), &font, "Hg");
+        let inked: Vec<(f32, f32)> = canvas
+            .pixels()
+            .chunks(4)
+            .enumerate()
+            .filter(|(_, p)| p[3] > 127)
+            .map(|(i, _)| ((i % 40) as f32 + 0.5, (i / 40) as f32 + 0.5))
+            .collect();
+        for (px, py) in inked.iter() {
+            assert!(*px > x && *px < x + w && *py > y && *py < y + h);
+        }
+        let left = inked.iter().fold(40.0f32, |m, p| m.min(p.0));
+        let bottom = inked.iter().fold(0.0f32, |m, p| m.max(p.1));
+        assert!(left - x < 1.5 && y + h - bottom < 1.5);
+    }
+
+    #[test]
+    fn glyph_cache() {
+        let font = FontGroup::default();
+        let mut image = Image::with_format(Size(64, 16), Format::Gray8);
+        let white = [255, 255, 255, 255];
+        let draw = |image: &mut Image, xysize| {
+            let mut buffer = vec![0; 64 * 16];
+            let pen = image.text(white, xysize, &font, "Hi, you!", &mut buffer);
+            (buffer, pen.unwrap())
+        };
+
+        // Cached glyphs look the same as rendered ones, and end at the same
+        // pen position (even scaled).
+        let (rendered, end) = draw(&mut image, (0.0, 2.0, 12.0));
+        image.set_glyph_cache(Some(GlyphCache::new(1 << 20)));
+        let (cached, pen) = draw(&mut image, (0.0, 2.0, 12.0));
+        let (again, _) = draw(&mut image, (0.0, 2.0, 12.0));
+        assert_eq!(pen, end);
+        assert_eq!(cached, again);
+        // Footile's coverage varies a little with the rest of the row.
+        let diff = rendered.iter().zip(&cached).map(|(a, b)| {
+            (i32::from(*a) - i32::from(*b)).abs()
+        });
+        let total = rendered.iter().map(|a| i32::from(*a));
+        assert!(diff.sum::<i32>() * 16 < total.sum::<i32>());
+        image.push_clip_rect((0.0, 0.0, 16.0, 16.0));
+        let (clipped, _) = draw(&mut image, (0.0, 2.0, 12.0));
+        let outside = clipped.chunks(64).flat_map(|row| &row[16..]);
+        assert!(outside.into_iter().all(|c| *c == 0));
+        image.pop_clip();
+        image.scale(2.0, 2.0);
+        assert_eq!(draw(&mut image, (0.0, 1.0, 6.0)).1, (end.0 / 2.0, 1.0));
+
+        // Fonts are cached separately by id.
+        let memory = image.glyph_cache().unwrap().memory();
+        image.set_font_id(1);
+        draw(&mut image, (0.0, 1.0, 6.0));
+        let cache = image.glyph_cache().unwrap();
+        assert!(cache.memory() > memory);
+        let memory = cache.memory();
+        image.set_font_id(0);
+        draw(&mut image, (0.0, 1.0, 6.0));
+        assert_eq!(image.glyph_cache().unwrap().memory(), memory);
+
+        // Glyphs used least recently are dropped to stay within the budget.
+        image.set_glyph_cache(Some(GlyphCache::new(4096)));
+        for size in 8..32 {
+            draw(&mut image, (0.0, 0.0, size as f32));
+            let cache = image.glyph_cache().unwrap();
+            assert!(cache.memory() > 0 && cache.memory() <= 4096);
+        }
+    }
+
+    #[test]
+    fn truncate_text() {
+        // 8 pixels per letter (and ellipsis) at size 16.
+        let font = FontGroup::default();
+        let cut = |text, width, ellipsis| {
+            truncate(&font, 16.0, text, width, ellipsis)
+        };
+
+        assert_eq!(cut("Short", 40.0, Ellipsis::End), "Short");
+        assert_eq!(cut("Longer", 40.0, Ellipsis::End), "Long…");
+        assert_eq!(cut("Longer", 40.0, Ellipsis::Middle), "Lo…er");
+        assert_eq!(cut("Two words", 40.0, Ellipsis::End), "Two…");
+        assert_eq!(cut("Longer", 7.0, Ellipsis::End), "");
+
+        // Accents (combining marks) stay with their letters.
+        let accents = "e\u{301}e\u{301}e\u{301}e\u{301}";
+        assert_eq!(cut(accents, 24.0, Ellipsis::End), "e\u{301}e\u{301}…");
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png() {
+        let mut canvas = Canvas::new(Size(3, 2));
+        let pixels: Vec<u8> = (0..3 * 2 * 4).map(|i| i * 10).collect();
+        canvas.pixels_mut().copy_from_slice(&pixels);
+
+        let mut data = vec![];
+        canvas.write_png(&mut data).unwrap();
+        let loaded = Canvas::read_png(&data[..]).unwrap();
+        assert_eq!(loaded.size(), Size(3, 2));
+        assert_eq!(loaded.pixels(), &pixels[..]);
+        assert!(Canvas::read_png(&data[1..]).is_err());
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn golden() {
+        let golden = |name: &str| {
+            let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+            Golden::new(format!("{}/{}.png", dir, name)).tolerance(2)
+        };
+        #[rustfmt::skip]
+        let square = [
+            Move(0.0, 0.0), Line(64.0, 0.0), Line(64.0, 64.0), Line(0.0, 64.0),
+        ];
+
+        // Drawing on an image.
+        let mut canvas = Canvas::new(Size(64, 64));
+        let stops = [(0.0, [255, 200, 0, 255]), (1.0, [0, 80, 200, 255])];
+        let paint = Paint::Radial((32.0, 32.0), 32.0, &stops, Spread::Pad);
+        canvas.fill(paint, &square);
+        canvas.image_mut().push_clip_rect((0.0, 0.0, 64.0, 40.0));
+        canvas.stroke(
+            [0, 0, 0, 255],
+            &[PenWidth(4.0), Move(8.0, 8.0), Line(56.0, 56.0)],
+        );
+        canvas.image_mut().pop_clip();
+        golden("drawing").check(&canvas).unwrap();
+
+        // A GUI.
+        let mut gui = Gui::new(FontGroup::default());
+        let mut canvas = Canvas::new(Size(160, 120));
+        let (image, pixels) = canvas.parts_mut();
+        gui.page(image, pixels, &|row| match row {
+            0 => Some(Row::default().text("On").toggle(true)),
+            1 => Some(Row::default().text("Off").toggle(false)),
+            _ => None,
+        })
+        .unwrap();
+        gui.head(image, pixels, &|_| Some(Row::default().button("OK")))
+            .unwrap();
+        golden("gui").check(&canvas).unwrap();
+
+        // A different image fails, and saves the differences.
+        let dir = std::env::temp_dir().join("barg-golden");
+        std::fs::create_dir_all(&dir).unwrap();
+        let reference = Golden::new(dir.join("gui.png"));
+        reference.bless(&canvas).unwrap();
+        let (image, pixels) = canvas.parts_mut();
+        image.fill([255, 0, 0, 255], &square[..3], pixels).unwrap();
+        match reference.compare(&canvas) {
+            Err(Error::Mismatch { pixels, diff }) => {
+                assert!(pixels > 0);
+                assert_eq!(diff, dir.join("gui.diff.png"));
+                assert!(diff.exists() && dir.join("gui.actual.png").exists());
+            }
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gui_page() {
+        use std::cell::RefCell;
+
+        let mut gui = Gui::new(FontGroup::default());
+        let mut image = Image::new(Size(200, 120));
+        let mut buffer = vec![0; 200 * 120 * 4];
+        let asked = RefCell::new(vec![]);
+        let mut page = |gui: &mut Gui, rows: usize| {
+            asked.borrow_mut().clear();
+            gui.page(&mut image, &mut buffer, &|row| {
+                asked.borrow_mut().push(row);
+                if row < rows {
+                    Some(Row::default().text("Row"))
+                } else {
+                    None
+                }
+            })
+            .unwrap();
+            asked.borrow().clone()
+        };
+
+        // Only rows that fit below the 37 pixel high head are generated.
+        assert_eq!(page(&mut gui, 1000), [0, 1, 2]);
+        // Scrolled part way into the second row.
+        gui.scroll(40);
+        assert_eq!(page(&mut gui, 1000), [1, 2, 3]);
+        gui.scroll(36 * 500);
+        assert_eq!(page(&mut gui, 1000), [501, 502, 503]);
+        // A row scrolled all the way behind the head isn't generated.
+        gui.scroll(-36 * 1000);
+        gui.scroll(35);
+        assert_eq!(page(&mut gui, 1000), [1, 2, 3]);
+        // Generating stops at the first `None`.
+        gui.scroll(-36 * 1000);
+        assert_eq!(page(&mut gui, 2), [0, 1, 2]);
+    }
+
+    #[test]
+    fn gui_hit() {
+        let mut gui = Gui::new(FontGroup::default());
+        let mut image = Image::new(Size(200, 120));
+        let mut buffer = vec![0; 200 * 120 * 4];
+        gui.page(&mut image, &mut buffer, &|row| {
+            if row < 10 {
+                Some(Row::new([255, 255, 255, 255]).text("Row"))
+            } else {
+                None
+            }
+        }).unwrap();
+        gui.head(&mut image, &mut buffer, &|_row| {
+            Some(Row::new([0, 0, 0, 255]).text("Head").text("Two"))
+        }).unwrap();
+        let hit = gui.hit(2.0, 2.0).unwrap();
+        assert_eq!(hit.region, Region::Head);
+        assert_eq!(hit.column, Some(0));
+        assert_eq!(gui.hit(199.0, 2.0).unwrap().column, None);
+        assert_eq!(gui.hit(2.0, 40.0).unwrap().row, 0);
+        assert_eq!(gui.hit(2.0, 80.0).unwrap().row, 1);
+
+        gui.scroll(36);
+        gui.page(&mut image, &mut buffer, &|row| {
+            if row < 10 {
+                Some(Row::new([255, 255, 255, 255]).text("Row"))
+            } else {
+                None
+            }
+        }).unwrap();
+        assert_eq!(gui.hit(2.0, 40.0).unwrap().row, 1);
+        assert_eq!(gui.hit(2.0, 40.0).unwrap().region, Region::Page);
+
+        gui.popup(&mut image, &mut buffer, &|row| {
+            if row == 0 {
+                Some(Row::new([255, 255, 255, 255]).text("Ok?"))
+            } else {
+                None
+            }
+        }).unwrap();
+        assert_eq!(gui.hit(2.0, 2.0), None);
+        assert_eq!(gui.hit(100.0, 60.0).unwrap().region, Region::Popup);
+        gui.close_popup();
+        assert_eq!(gui.hit(2.0, 2.0).unwrap().region, Region::Head);
+    }
+
+    #[test]
+    fn gui_popup() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let mut gui = Gui::new(FontGroup::default());
+        let mut image = Image::new(Size(200, 240));
+        let mut buffer = vec![0; 200 * 240 * 4];
+        let rows = |row| {
+            if row < 10 {
+                Some(Row::new([255, 255, 255, 255]).text("Row"))
+            } else {
+                None
+            }
+        };
+        gui.page(&mut image, &mut buffer, &rows).unwrap();
+        let pixel = |buffer: &[u8]| buffer[(60 * 200 + 190) * 4];
+        assert_eq!(pixel(&buffer), 255);
+
+        let presses = Rc::new(Cell::new((0, 0)));
+        let page = gui.add(Region::Page, 0, None);
+        let count = presses.clone();
+        gui.press(page, Box::new(move || {
+            count.set((count.get().0 + 1, count.get().1))
+        }));
+        let popup = gui.add(Region::Popup, 0, None);
+        let count = presses.clone();
+        gui.press(popup, Box::new(move || {
+            count.set((count.get().0, count.get().1 + 1))
+        }));
+        let tap = |gui: &mut Gui, x, y| {
+            gui.input(Input::Press(x, y));
+            gui.input(Input::Release(x, y));
+        };
+
+        // As many rows as fit with a row of space above and below (4 of 10),
+        // centered.
+        gui.popup(&mut image, &mut buffer, &rows).unwrap();
+        assert_eq!(gui.hit(100.0, 47.0), None);
+        assert_eq!(gui.hit(100.0, 49.0).unwrap().row, 0);
+        let hit = gui.hit(100.0, 191.0).unwrap();
+        assert_eq!((hit.region, hit.row), (Region::Popup, 3));
+        assert_eq!(gui.hit(100.0, 193.0), None);
+        assert_eq!(gui.hit(20.0, 100.0), None);
+        // The rest of the GUI is dimmed.
+        assert!(pixel(&buffer) < 255);
+
+        // Taps outside of the popup don't reach the page.
+        tap(&mut gui, 190.0, 40.0);
+        assert_eq!(presses.get(), (0, 0));
+        tap(&mut gui, 100.0, 60.0);
+        assert_eq!(presses.get(), (0, 1));
+        gui.close_popup();
+        tap(&mut gui, 190.0, 40.0);
+        assert_eq!(presses.get(), (1, 1));
+    }
+
+    #[test]
+    fn gui_sidebar() {
+        let mut gui = Gui::new(FontGroup::default());
+        let mut image = Image::new(Size(200, 120));
+        let mut buffer = vec![0; 200 * 120 * 4];
+        let rows = |row| {
+            if row < 10 {
+                Some(Row::default().text("Row"))
+            } else {
+                None
+            }
+        };
+        let mut draw = |gui: &mut Gui| {
+            gui.page(&mut image, &mut buffer, &rows).unwrap();
+            gui.sidebar(&mut image, &mut buffer, &rows).unwrap();
+        };
+
+        // The page is narrower, to make room for the sidebar on the left.
+        gui.set_sidebar(60, Side::Left);
+        draw(&mut gui);
+        let hit = gui.hit(59.0, 40.0).unwrap();
+        assert_eq!((hit.region, hit.row), (Region::Sidebar, 0));
+        let hit = gui.hit(61.0, 40.0).unwrap();
+        assert_eq!((hit.region, hit.row), (Region::Page, 0));
+        assert_eq!(hit.column, Some(0));
+
+        // The sidebar scrolls separately from the page.
+        gui.scroll_sidebar(36);
+        draw(&mut gui);
+        assert_eq!(gui.hit(10.0, 40.0).unwrap().row, 1);
+        assert_eq!(gui.hit(100.0, 40.0).unwrap().row, 0);
+        gui.scroll(72);
+        draw(&mut gui);
+        assert_eq!(gui.hit(10.0, 40.0).unwrap().row, 1);
+        assert_eq!(gui.hit(100.0, 40.0).unwrap().row, 2);
+
+        // Or on the right.
+        gui.set_sidebar(60, Side::Right);
+        draw(&mut gui);
+        assert_eq!(gui.hit(150.0, 40.0).unwrap().region, Region::Sidebar);
+        assert_eq!(gui.hit(10.0, 40.0).unwrap().region, Region::Page);
+        assert_eq!(gui.hit(139.0, 40.0).unwrap().region, Region::Page);
+    }
+
+    #[test]
+    fn gui_input() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let presses = Rc::new(Cell::new(0));
+        let swipes = Rc::new(Cell::new(0));
+
+        let mut gui = Gui::new(FontGroup::default());
+        let mut image = Image::new(Size(200, 120));
+        let mut buffer = vec![0; 200 * 120 * 4];
+        gui.page(&mut image, &mut buffer, &|row| {
+            if row < 10 {
+                Some(Row::new([255, 255, 255, 255]).text("Row"))
+            } else {
+                None
+            }
+        }).unwrap();
+
+        let row = gui.add(Region::Page, 1, None);
+        let count = presses.clone();
+        gui.press(row, Box::new(move || count.set(count.get() + 1)));
+        let count = swipes.clone();
+        gui.swipe(row, Box::new(move |edge, done, x, _y| {
+            assert!(!edge);
+            if done {
+                assert!(x > 0.25);
+                count.set(count.get() + 1);
+            }
+        }));
+
+        // Tap.
+        gui.input(Input::Press(50.0, 80.0));
+        gui.input(Input::Release(51.0, 80.0));
+        assert_eq!(presses.get(), 1);
+        // Different row.
+        gui.input(Input::Press(50.0, 40.0));
+        gui.input(Input::Release(50.0, 40.0));
+        assert_eq!(presses.get(), 1);
+        // Released somewhere else, without dragging (a swipe, not a press).
+        gui.input(Input::Press(50.0, 80.0));
+        gui.input(Input::Release(190.0, 10.0));
+        assert_eq!(presses.get(), 1);
+        assert_eq!(swipes.get(), 1);
+        // Swipe right.
+        gui.input(Input::Press(50.0, 80.0));
+        gui.input(Input::Drag(80.0, 80.0));
+        gui.input(Input::Release(150.0, 80.0));
+        assert_eq!(presses.get(), 1);
+        assert_eq!(swipes.get(), 2);
+        // Removed widgets don't get input.
+        gui.remove(row);
+        gui.input(Input::Press(50.0, 80.0));
+        gui.input(Input::Release(51.0, 80.0));
+        gui.input(Input::Press(50.0, 80.0));
+        gui.input(Input::Release(150.0, 80.0));
+        assert_eq!(presses.get(), 1);
+        assert_eq!(swipes.get(), 2);
+    }
+
+    #[test]
+    fn gui_columns() {
+        let mut gui = Gui::new(FontGroup::default());
+        let mut image = Image::new(Size(200, 120));
+        let mut buffer = vec![0; 200 * 120 * 4];
+        let square: &[PathOp] = &[
+            Move(0.0, 0.0),
+            Line(8.0, 0.0),
+            Line(8.0, 8.0),
+            Line(0.0, 8.0),
+        ];
+        let icon = [([255, 0, 0, 255], square)];
+        let red = |buffer: &[u8], x: usize, y: usize| {
+            buffer[(y * 200 + x) * 4..][..4] == [255, 0, 0, 255]
+        };
+
+        // 50 pixels, a quarter of 200 pixels, and fit to "Abc" (3 letters
+        // 13.5 pixels wide, plus 4.5 pixels of padding on each side).
+        gui.set_columns(Region::Page, &[
+            Column::new(Width::Fixed(50.0), Align::Left),
+            Column::new(Width::Fraction(0.25), Align::Left),
+            Column::default(),
+        ]);
+        gui.page(&mut image, &mut buffer, &|_| {
+            Some(Row::default().text("Abc").text("Abc").text("Abc"))
+        })
+        .unwrap();
+        let column = |x| gui.hit(x, 40.0).unwrap().column;
+        assert_eq!(column(49.0), Some(0));
+        assert_eq!(column(51.0), Some(1));
+        assert_eq!(column(99.0), Some(1));
+        assert_eq!(column(101.0), Some(2));
+        assert_eq!(column(149.0), Some(2));
+        assert_eq!(column(150.0), None);
+
+        // Icons are placed in their column: centered from 66 to 74 pixels,
+        // and against the right (less padding) from 117.5 to 125.5 pixels.
+        gui.set_columns(Region::Page, &[
+            Column::new(Width::Fixed(50.0), Align::Left),
+            Column::new(Width::Fixed(40.0), Align::Center),
+            Column::new(Width::Fixed(40.0), Align::Right),
+        ]);
+        gui.page(&mut image, &mut buffer, &|_| {
+            Some(Row::default().spacer(0.0).icon(&icon).icon(&icon))
+        })
+        .unwrap();
+        assert!(!red(&buffer, 4, 40));
+        assert!(!red(&buffer, 64, 40));
+        assert!(red(&buffer, 66, 40) && red(&buffer, 73, 40));
+        assert!(!red(&buffer, 75, 40));
+        assert!(!red(&buffer, 116, 40));
+        assert!(red(&buffer, 118, 40) && red(&buffer, 124, 40));
+        assert!(!red(&buffer, 127, 40));
+    }
+
+    #[test]
+    fn gui_scale() {
+        let mut gui = Gui::new(FontGroup::default());
+        let mut image = Image::new(Size(200, 240));
+        let mut buffer = vec![0; 200 * 240 * 4];
+        gui.set_size(30);
+        gui.set_scale(2.0);
+        gui.page(&mut image, &mut buffer, &|row| {
+            if row < 10 {
+                Some(Row::default().text("Row"))
+            } else {
+                None
+            }
+        }).unwrap();
+        // Rows are 60 pixels high, under a 60 pixel high head.
+        assert_eq!(gui.hit(2.0, 70.0).unwrap().row, 0);
+        assert_eq!(gui.hit(2.0, 130.0).unwrap().row, 1);
+        assert_eq!(gui.hit(2.0, 190.0).unwrap().row, 2);
+    }
+
+    #[test]
+    fn text_field() {
+        let mut field = TextField::new("añb");
+        assert_eq!(field.cursor(), 4);
+        field.input(Input::Edit(Edit::Left, false));
+        field.input(Input::Edit(Edit::Left, true));
+        assert_eq!(field.selection(), 1..3);
+        assert!(field.input(Input::Text('c')));
+        assert_eq!(field.text(), "acb");
+        field.input(Input::Edit(Edit::Home, false));
+        assert!(!field.input(Input::Edit(Edit::Backspace, false)));
+        assert!(field.input(Input::Edit(Edit::Delete, false)));
+        field.input(Input::Edit(Edit::End, true));
+        assert_eq!(field.selection(), 0..2);
+        field.input(Input::Edit(Edit::Backspace, false));
+        assert_eq!(field.text(), "");
+    }
 }
 
 // Initialize graphic shader.
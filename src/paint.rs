@@ -0,0 +1,296 @@
+//! Paints for filling, stroking and drawing text on an `Image`.
+
+use crate::transform::{invert, IDENTITY};
+use crate::{Format, Size};
+
+/// How a gradient continues before its first stop and after its last stop,
+/// or how a pattern continues past its edges.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Spread {
+    /// Continue with the color of the nearest end (or edge).
+    Pad,
+    /// Start over from the first stop (or tile the pattern).
+    Repeat,
+    /// Go back and forth between the first and last stop (or tile the pattern,
+    /// mirroring every other tile).
+    Reflect,
+}
+
+/// How to pick the color of a pattern between its pixels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Sampling {
+    /// Use the closest pixel (blocky when scaled up).
+    Nearest,
+    /// Blend the 4 closest pixels (smooth when scaled up).
+    Bilinear,
+}
+
+/// Pixels (sRGBA) of another image, used as a `Paint`.
+///
+/// By default, pixel (0, 0) of the pattern is at (0, 0) of the `Image`, and
+/// the pattern is padded past its edges with `Sampling::Bilinear`.
+///
+/// ```
+/// use barg::{Image, Line, Move, Pattern, Size};
+///
+/// let mut image = Image::new(Size(64, 64));
+/// let mut buffer = vec![0; 64 * 64 * 4];
+/// // A 2x2 checkerboard.
+/// let photo = [
+///     255, 255, 255, 255, 0, 0, 0, 255,
+///     0, 0, 0, 255, 255, 255, 255, 255,
+/// ];
+/// let square = [
+///     Move(16.0, 16.0),
+///     Line(48.0, 16.0),
+///     Line(48.0, 48.0),
+///     Line(16.0, 48.0),
+/// ];
+///
+/// // Stretch the photo over the square.
+/// let pattern = Pattern::new(&photo, Size(2, 2))
+///     .stretch((16.0, 16.0, 32.0, 32.0));
+/// image.fill(pattern, &square, &mut buffer).unwrap();
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pattern<'a> {
+    // Pixels, row by row.
+    pixels: &'a [u8],
+    // Width and height of the pattern, in pixels.
+    size: Size,
+    // How the pixels are stored.
+    format: Format,
+    // Affine matrix from image coordinates to pattern coordinates.
+    inverse: [f32; 6],
+    // How the pattern continues past its edges.
+    spread: Spread,
+    // How to pick colors between pixels.
+    sampling: Sampling,
+}
+
+impl<'a> Pattern<'a> {
+    /// Create a new pattern from `pixels` (sRGBA, unless changed with
+    /// `format()`), which are `size` pixels.  Pixels past the end of the
+    /// slice are transparent.
+    pub fn new(pixels: &'a [u8], size: Size) -> Self {
+        Pattern {
+            pixels,
+            size,
+            format: Format::Rgba8,
+            inverse: IDENTITY,
+            spread: Spread::Pad,
+            sampling: Sampling::Bilinear,
+        }
+    }
+
+    /// Place the pattern with an affine `matrix` [a, b, c, d, e, f], which
+    /// maps pattern coordinates (x, y) to image coordinates
+    /// (a·x + c·y + e, b·x + d·y + f).  If the matrix can't be inverted,
+    /// the whole path gets the color of pixel (0, 0).
+    pub fn transform(self, matrix: [f32; 6]) -> Self {
+        Pattern {
+            inverse: invert(matrix).unwrap_or([0.0; 6]),
+            ..self
+        }
+    }
+
+    /// Stretch the pattern to fill a rectangle (x, y, width, height).
+    pub fn stretch(self, rect: (f32, f32, f32, f32)) -> Self {
+        let (x, y, w, h) = rect;
+        let (pw, ph) = (f32::from(self.size.0), f32::from(self.size.1));
+        self.transform([w / pw, 0.0, 0.0, h / ph, x, y])
+    }
+
+    /// Set how the pattern continues past its edges.
+    pub fn spread(self, spread: Spread) -> Self {
+        Pattern { spread, ..self }
+    }
+
+    /// Set how the pixels are stored.
+    pub fn format(self, format: Format) -> Self {
+        Pattern { format, ..self }
+    }
+
+    /// Set how to pick the color between pixels.
+    pub fn sampling(self, sampling: Sampling) -> Self {
+        Pattern { sampling, ..self }
+    }
+
+    // Get the width and height of the pattern.
+    pub(crate) fn size(&self) -> Size {
+        self.size
+    }
+
+    // Set the matrix from image coordinates to pattern coordinates.
+    pub(crate) fn inverse(self, inverse: [f32; 6]) -> Self {
+        Pattern { inverse, ..self }
+    }
+
+    // Get the color at pixel coordinates (`x`, `y`) of the image.
+    fn color_at(&self, x: f32, y: f32) -> [u8; 4] {
+        let m = self.inverse;
+        let u = m[0] * x + m[2] * y + m[4];
+        let v = m[1] * x + m[3] * y + m[5];
+
+        match self.sampling {
+            Sampling::Nearest => self.pixel(u.floor(), v.floor()),
+            Sampling::Bilinear => {
+                // Pixel centers are at half pixels.
+                let (u, v) = (u - 0.5, v - 0.5);
+                let (x, y) = (u.floor(), v.floor());
+                let (fx, fy) = (u - x, v - y);
+                let top = lerp(self.pixel(x, y), self.pixel(x + 1.0, y), fx);
+                let bottom = lerp(
+                    self.pixel(x, y + 1.0),
+                    self.pixel(x + 1.0, y + 1.0),
+                    fx,
+                );
+                lerp(top, bottom, fy)
+            }
+        }
+    }
+
+    // Get the pixel at (`x`, `y`), following the spread past the edges.
+    fn pixel(&self, x: f32, y: f32) -> [u8; 4] {
+        let (w, h) = (i64::from(self.size.0), i64::from(self.size.1));
+        if w == 0 || h == 0 {
+            return [0; 4];
+        }
+        let wrap = |i: i64, n: i64| match self.spread {
+            Spread::Pad => i.max(0).min(n - 1),
+            Spread::Repeat => i.rem_euclid(n),
+            Spread::Reflect => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+        };
+        let (x, y) = (wrap(x as i64, w), wrap(y as i64, h));
+        let bpp = self.format.bytes_per_pixel();
+        let i = (y * w + x) as usize * bpp;
+
+        match self.pixels.get(i..i + bpp) {
+            Some(p) => self.format.rgba(p),
+            None => [0; 4],
+        }
+    }
+}
+
+/// What to paint the inside of a path (or text) with.  Colors are sRGBA, and
+/// coordinates are the same as the path's, so paints move with the `Image`
+/// transform.
+///
+/// Gradient `stops` are (offset, color) pairs, sorted by offset, where an
+/// offset of 0 is the start of the gradient and 1 is the end.  Colors are
+/// interpolated between stops.
+///
+/// ```
+/// use barg::{Image, Line, Move, Paint, Size, Spread};
+///
+/// let mut image = Image::new(Size(64, 64));
+/// let mut buffer = vec![0; 64 * 64 * 4];
+/// let stops = [(0.0, [255, 0, 0, 255]), (1.0, [0, 0, 255, 255])];
+/// let square = [
+///     Move(0.0, 0.0),
+///     Line(64.0, 0.0),
+///     Line(64.0, 64.0),
+///     Line(0.0, 64.0),
+/// ];
+///
+/// // Red on the left, to blue on the right.
+/// let paint = Paint::Linear((0.0, 0.0), (64.0, 0.0), &stops, Spread::Pad);
+/// image.fill(paint, &square, &mut buffer).unwrap();
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Paint<'a> {
+    /// A single color.
+    Solid([u8; 4]),
+    /// A linear gradient from a start point to an end point.
+    Linear((f32, f32), (f32, f32), &'a [(f32, [u8; 4])], Spread),
+    /// A radial gradient from a center point out to a radius.
+    Radial((f32, f32), f32, &'a [(f32, [u8; 4])], Spread),
+    /// Pixels of another image.
+    Pattern(Pattern<'a>),
+}
+
+impl<'a> From<Pattern<'a>> for Paint<'a> {
+    fn from(pattern: Pattern<'a>) -> Self {
+        Paint::Pattern(pattern)
+    }
+}
+
+impl<'a> From<[u8; 4]> for Paint<'a> {
+    fn from(color: [u8; 4]) -> Self {
+        Paint::Solid(color)
+    }
+}
+
+impl<'a> Paint<'a> {
+    // Get the color at pixel coordinates (`x`, `y`).
+    pub(crate) fn color_at(&self, x: f32, y: f32) -> [u8; 4] {
+        match *self {
+            Paint::Solid(color) => color,
+            Paint::Linear(start, end, stops, spread) => {
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let length = dx * dx + dy * dy;
+                let t = if length > 0.0 {
+                    ((x - start.0) * dx + (y - start.1) * dy) / length
+                } else {
+                    0.0
+                };
+                gradient(stops, spread, t)
+            }
+            Paint::Radial(center, radius, stops, spread) => {
+                let (dx, dy) = (x - center.0, y - center.1);
+                let t = if radius > 0.0 {
+                    (dx * dx + dy * dy).sqrt() / radius
+                } else {
+                    1.0
+                };
+                gradient(stops, spread, t)
+            }
+            Paint::Pattern(ref pattern) => pattern.color_at(x, y),
+        }
+    }
+}
+
+// Interpolate between two colors (sRGBA) by `amount` (0 to 1).
+fn lerp(a: [u8; 4], b: [u8; 4], amount: f32) -> [u8; 4] {
+    let mut color = [0; 4];
+    for (i, channel) in color.iter_mut().enumerate() {
+        let (a, b) = (f32::from(a[i]), f32::from(b[i]));
+        *channel = (a + (b - a) * amount).round() as u8;
+    }
+    color
+}
+
+// Get the color of a gradient at offset `t`.
+fn gradient(stops: &[(f32, [u8; 4])], spread: Spread, t: f32) -> [u8; 4] {
+    let t = match spread {
+        Spread::Pad => t,
+        Spread::Repeat => t - t.floor(),
+        Spread::Reflect => {
+            let t = t.rem_euclid(2.0);
+            if t > 1.0 {
+                2.0 - t
+            } else {
+                t
+            }
+        }
+    };
+
+    // Find the stops on either side of `t`.
+    let index = stops.iter().position(|stop| stop.0 >= t);
+    let (a, b) = match index {
+        Some(0) => return stops[0].1,
+        Some(i) => (stops[i - 1], stops[i]),
+        None => return stops.last().map(|s| s.1).unwrap_or([0; 4]),
+    };
+
+    // Interpolate between them.
+    let amount = if b.0 > a.0 { (t - a.0) / (b.0 - a.0) } else { 1.0 };
+    lerp(a.1, b.1, amount)
+}
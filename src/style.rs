@@ -0,0 +1,374 @@
+//! Fill rules and stroke styles for an `Image`.
+
+use crate::footile;
+use fonterator::PathOp::{self, *};
+
+// Shortest dash (or gap) that isn't 0.
+const MIN_DASH: f32 = 1.0 / 16.0;
+
+/// How to decide which parts of a path are inside it, for `Image::fill()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FillRule {
+    /// Inside where the path winds around a point any number of times.
+    NonZero,
+    /// Inside where the path winds around a point an odd number of times, so
+    /// overlapping parts make holes.
+    EvenOdd,
+}
+
+impl From<FillRule> for footile::FillRule {
+    fn from(rule: FillRule) -> Self {
+        match rule {
+            FillRule::NonZero => footile::FillRule::NonZero,
+            FillRule::EvenOdd => footile::FillRule::EvenOdd,
+        }
+    }
+}
+
+/// How to draw corners where lines of a stroke meet.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Join {
+    /// Sharp corners, beveled where the miter would be longer than the limit
+    /// (miter length to stroke width ratio).
+    Miter(f32),
+    /// Rounded corners.
+    Round,
+    /// Corners cut off flat.
+    Bevel,
+}
+
+/// How to draw the ends of a stroke that isn't closed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Cap {
+    /// End flat at the end point.
+    Butt,
+    /// End with a half circle past the end point.
+    Round,
+    /// End flat, half the stroke width past the end point.
+    Square,
+}
+
+/// How to draw strokes with `Image::stroke()`.  Stroke width is still set
+/// with `PenWidth` in the path.
+///
+/// ```
+/// use barg::{Cap, Image, Join, Line, Move, PenWidth, Size, StrokeStyle};
+///
+/// let mut image = Image::new(Size(64, 64));
+/// let mut buffer = vec![0; 64 * 64 * 4];
+///
+/// // A dashed line: 8 pixels on, then 4 pixels off.
+/// image.set_stroke_style(StrokeStyle {
+///     join: Join::Round,
+///     cap: Cap::Round,
+///     dashes: vec![8.0, 4.0],
+///     ..StrokeStyle::default()
+/// });
+/// image.stroke(
+///     [0, 0, 0, 255],
+///     &[PenWidth(4.0), Move(8.0, 8.0), Line(56.0, 56.0)],
+///     &mut buffer,
+/// ).unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    /// How lines meet.
+    pub join: Join,
+    /// How open strokes end.
+    pub cap: Cap,
+    /// Lengths of dashes and the gaps between them (starting with a dash),
+    /// which repeat along the stroke.  Empty for a solid stroke.  If there's
+    /// an odd number of lengths, they are repeated to make an even number.
+    /// Lengths other than 0 are at least 1/16.
+    pub dashes: Vec<f32>,
+    /// How far into the dash pattern the stroke starts.
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            join: Join::Miter(4.0),
+            cap: Cap::Butt,
+            dashes: vec![],
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    // Check if a path has to be changed before footile can stroke it.
+    pub(crate) fn is_plain(&self) -> bool {
+        self.cap == Cap::Butt && self.dashes().is_none()
+    }
+
+    // Get the dash pattern, if dashed.
+    fn dashes(&self) -> Option<Vec<f32>> {
+        let mut dashes = self.dashes.clone();
+        if dashes.iter().any(|d| *d < 0.0) || dashes.iter().sum::<f32>() <= 0.0
+        {
+            return None;
+        }
+        if dashes.len() % 2 == 1 {
+            dashes.extend_from_slice(&self.dashes);
+        }
+        // Tiny dashes would split a line into too many pieces.
+        for dash in dashes.iter_mut().filter(|d| **d > 0.0) {
+            *dash = dash.max(MIN_DASH);
+        }
+        Some(dashes)
+    }
+
+    // Apply dashes and caps to a path, starting with pen width `width`.
+    // Returns the path to stroke, the path to fill for round caps, and the
+    // pen width at the end of the path.
+    pub(crate) fn apply<'a, T>(
+        &self,
+        path: T,
+        width: f32,
+    ) -> (Vec<PathOp>, Vec<PathOp>, f32)
+    where
+        T: IntoIterator<Item = &'a PathOp>,
+    {
+        let (mut lines, width) = flatten(path, width);
+        if let Some(dashes) = self.dashes() {
+            lines = lines
+                .iter()
+                .flat_map(|line| dash(line, &dashes, self.dash_offset))
+                .collect();
+        }
+
+        let mut stroke = vec![];
+        let mut caps = vec![];
+        for mut line in lines {
+            if !line.closed && line.points.len() > 1 {
+                match self.cap {
+                    Cap::Butt => {}
+                    Cap::Square => square_caps(&mut line.points),
+                    Cap::Round => {
+                        circle(&mut caps, line.points[0]);
+                        circle(&mut caps, line.points[line.points.len() - 1]);
+                    }
+                }
+            }
+            line.path(&mut stroke);
+        }
+        (stroke, caps, width)
+    }
+}
+
+// A point (x, y) with the pen width there.
+type Point = (f32, f32, f32);
+
+// A flattened sub-path (only straight lines).
+struct Lines {
+    points: Vec<Point>,
+    closed: bool,
+}
+
+impl Lines {
+    // Add the sub-path to a path.
+    fn path(&self, path: &mut Vec<PathOp>) {
+        let mut width = None;
+        for (i, &(x, y, w)) in self.points.iter().enumerate() {
+            if width != Some(w) {
+                path.push(PenWidth(w));
+                width = Some(w);
+            }
+            path.push(if i == 0 { Move(x, y) } else { Line(x, y) });
+        }
+        if self.closed {
+            path.push(Close());
+        }
+    }
+}
+
+// Split a path into sub-paths made of straight lines, starting with pen width
+// `width`.  Also returns the pen width at the end of the path.
+fn flatten<'a, T>(path: T, mut width: f32) -> (Vec<Lines>, f32)
+where
+    T: IntoIterator<Item = &'a PathOp>,
+{
+    let mut subs: Vec<Lines> = vec![];
+    let mut pen = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+
+    // Add points to the current sub-path, starting one at the pen if there
+    // isn't one.
+    let add = |subs: &mut Vec<Lines>, pen, points: &[(f32, f32)], w| {
+        if subs.last().map(|s| s.closed).unwrap_or(true) {
+            subs.push(Lines {
+                points: vec![],
+                closed: false,
+            });
+            add_points(subs, &[pen], w);
+        }
+        add_points(subs, points, w);
+    };
+
+    for op in path {
+        match *op {
+            Move(x, y) => {
+                subs.push(Lines {
+                    points: vec![(x, y, width)],
+                    closed: false,
+                });
+                pen = (x, y);
+                start = pen;
+            }
+            Line(x, y) => {
+                add(&mut subs, pen, &[(x, y)], width);
+                pen = (x, y);
+            }
+            Quad(bx, by, x, y) => {
+                let points = curve(&[pen, (bx, by), (x, y)]);
+                add(&mut subs, pen, &points, width);
+                pen = (x, y);
+            }
+            Cubic(bx, by, cx, cy, x, y) => {
+                let points = curve(&[pen, (bx, by), (cx, cy), (x, y)]);
+                add(&mut subs, pen, &points, width);
+                pen = (x, y);
+            }
+            Close() => {
+                if let Some(sub) = subs.last_mut() {
+                    sub.closed = true;
+                }
+                pen = start;
+            }
+            PenWidth(w) => width = w,
+        }
+    }
+    (subs, width)
+}
+
+// Add points with pen width `w` to the last sub-path.
+fn add_points(subs: &mut [Lines], points: &[(f32, f32)], w: f32) {
+    if let Some(sub) = subs.last_mut() {
+        sub.points.extend(points.iter().map(|p| (p.0, p.1, w)));
+    }
+}
+
+// Get points along a Bézier curve, after the first control point.
+fn curve(controls: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    // About one line every 4 pixels.
+    let length: f32 = controls
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+        .sum();
+    let n = (length / 4.0).ceil().clamp(1.0, 64.0) as usize;
+
+    (1..=n)
+        .map(|i| {
+            // De Casteljau's algorithm.
+            let t = i as f32 / n as f32;
+            let mut points = controls.to_vec();
+            while points.len() > 1 {
+                for j in 0..points.len() - 1 {
+                    let (a, b) = (points[j], points[j + 1]);
+                    points[j] = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+                }
+                points.pop();
+            }
+            points[0]
+        })
+        .collect()
+}
+
+// Split a sub-path into dashes.
+fn dash(line: &Lines, dashes: &[f32], offset: f32) -> Vec<Lines> {
+    let mut points = line.points.clone();
+    if line.closed && !points.is_empty() {
+        points.push(points[0]);
+    }
+
+    // Find where in the pattern the sub-path starts.
+    let total: f32 = dashes.iter().sum();
+    let mut index = 0;
+    let mut left = offset.rem_euclid(total);
+    while left >= dashes[index] {
+        left -= dashes[index];
+        index = (index + 1) % dashes.len();
+    }
+    let mut left = dashes[index] - left;
+
+    let mut out = vec![];
+    let mut current: Option<Vec<Point>> = if index % 2 == 0 {
+        points.first().map(|p| vec![*p])
+    } else {
+        None
+    };
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        let mut done = 0.0;
+
+        // Split the line where dashes start and end (until they're too short
+        // to move along it).
+        while length - done > left {
+            if left > 0.0 && done + left == done {
+                break;
+            }
+            done += left;
+            let t = done / length;
+            let p = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t, a.2);
+            match current.take() {
+                Some(mut points) => {
+                    points.push(p);
+                    out.push(Lines {
+                        points,
+                        closed: false,
+                    });
+                }
+                None => current = Some(vec![p]),
+            }
+            index = (index + 1) % dashes.len();
+            left = dashes[index];
+        }
+        left -= length - done;
+        if let Some(points) = current.as_mut() {
+            points.push(b);
+        }
+    }
+    if let Some(points) = current {
+        out.push(Lines {
+            points,
+            closed: false,
+        });
+    }
+    out
+}
+
+// Extend both ends of a sub-path by half the pen width.
+fn square_caps(points: &mut [Point]) {
+    let extend = |end: Point, from: Point| {
+        let (dx, dy) = (end.0 - from.0, end.1 - from.1);
+        let length = dx.hypot(dy);
+        if length > 0.0 {
+            let scale = end.2 * 0.5 / length;
+            (end.0 + dx * scale, end.1 + dy * scale, end.2)
+        } else {
+            end
+        }
+    };
+    let last = points.len() - 1;
+
+    points[0] = extend(points[0], points[1]);
+    points[last] = extend(points[last], points[last - 1]);
+}
+
+// Add a circle the size of the pen at a point to a path.  Points are snapped
+// to a quarter pixel so that footile can always fill it.
+fn circle(path: &mut Vec<PathOp>, center: Point) {
+    const SEGMENTS: usize = 16;
+
+    let (x, y, r) = (center.0, center.1, center.2 * 0.5);
+    let snap = |v: f32| (v * 4.0).round() / 4.0;
+    for i in 0..SEGMENTS {
+        let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+        let (sin, cos) = angle.sin_cos();
+        let (px, py) = (snap(x + r * cos), snap(y + r * sin));
+        path.push(if i == 0 { Move(px, py) } else { Line(px, py) });
+    }
+    path.push(Close());
+}
@@ -0,0 +1,664 @@
+//! Laying out paragraphs of text for an `Image`.
+
+use crate::{FontGroup, Line, Move, Paint, PathOp, Quad};
+use std::mem;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How lines of a `Paragraph` line up.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextAlign {
+    /// Line up on the left.
+    Left,
+    /// Center each line.
+    Center,
+    /// Line up on the right.
+    Right,
+    /// Spread the words of each line to fill the width, except for the last
+    /// line (and lines that end with a newline), which line up on the left.
+    Justify,
+}
+
+/// Where `truncate()` shortens text with an ellipsis ("…").
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Ellipsis {
+    /// Keep the start of the text ("Long te…").
+    End,
+    /// Keep the start and end of the text ("Lon…ext").
+    Middle,
+}
+
+/// How to lay out text into a `Paragraph`, for `Image::paragraph()`.
+///
+/// ```
+/// use barg::{FontGroup, Image, Size, TextAlign, TextLayout};
+///
+/// let font = FontGroup::default();
+/// let mut image = Image::new(Size(200, 200));
+/// let mut buffer = vec![0; 200 * 200 * 4];
+///
+/// let paragraph = TextLayout::new(16.0)
+///     .width(180.0)
+///     .align(TextAlign::Justify)
+///     .lay_out(&font, "Some words that don't fit on one line.\nThe end.");
+///
+/// // The size is known before drawing.
+/// let (_, _, w, h) = paragraph.bbox();
+/// assert!(w <= 180.0);
+/// assert_eq!(h, 3.0 * 20.0);
+/// let black = [0, 0, 0, 255];
+/// image
+///     .paragraph(black, (10.0, 10.0), &font, &paragraph, &mut buffer)
+///     .unwrap();
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextLayout {
+    // Font size.
+    size: f32,
+    // Width to wrap at, if wrapping.
+    width: Option<f32>,
+    // How lines line up.
+    align: TextAlign,
+    // Distance between lines, as a multiple of the font size.
+    line_height: f32,
+}
+
+impl TextLayout {
+    /// Create a text layout with font `size`, which doesn't wrap, lines up
+    /// on the left, and has a line height of 1.25.
+    pub fn new(size: f32) -> Self {
+        TextLayout {
+            size,
+            width: None,
+            align: TextAlign::Left,
+            line_height: 1.25,
+        }
+    }
+
+    /// Wrap lines (between words) to fit `width`.  Words wider than `width`
+    /// get their own line.
+    pub fn width(self, width: f32) -> Self {
+        TextLayout {
+            width: Some(width),
+            ..self
+        }
+    }
+
+    /// Set how lines line up, within the width (or the widest line, if not
+    /// wrapping).
+    pub fn align(self, align: TextAlign) -> Self {
+        TextLayout { align, ..self }
+    }
+
+    /// Set the distance between lines, as a multiple of the font size.
+    pub fn line_height(self, line_height: f32) -> Self {
+        TextLayout {
+            line_height,
+            ..self
+        }
+    }
+
+    /// Lay out `text`, starting new lines at newlines (and when wrapping).
+    /// Runs of spaces between words become one space.
+    pub fn lay_out<'a>(
+        &self,
+        font: &FontGroup,
+        text: &'a str,
+    ) -> Paragraph<'a> {
+        self.lay_out_spans(font, &[Span::new(text)])
+    }
+
+    /// Lay out `spans` of rich text as one paragraph (see `lay_out()`).
+    /// Spans without their own font or size use `font` and the layout's
+    /// size.  Spans on a line share its baseline, and lines are as tall as
+    /// their biggest text.
+    pub fn lay_out_spans<'a>(
+        &self,
+        font: &FontGroup,
+        spans: &[Span<'a>],
+    ) -> Paragraph<'a> {
+        let lines = self.wrap(font, spans);
+        let widest = lines.iter().fold(0.0f32, |w, line| w.max(line.width));
+        let width = self.width.unwrap_or(widest);
+
+        // Place the words of each line.
+        let mut runs: Vec<Run> = vec![];
+        let (mut left, mut right) = (width, 0.0f32);
+        let mut top = 0.0;
+        for line in &lines {
+            let pieces = line.words.iter().flat_map(|word| &word.pieces);
+            let (ascent, size) = pieces.fold((0.0f32, 0.0f32), |m, piece| {
+                let size = spans[piece.span].size.unwrap_or(self.size);
+                (m.0.max(piece.ascent), m.1.max(size))
+            });
+            let baseline = top + ascent;
+            let size = if line.words.is_empty() { self.size } else { size };
+            top += size * self.line_height;
+
+            let mut x = match self.align {
+                TextAlign::Left | TextAlign::Justify => 0.0,
+                TextAlign::Center => (width - line.width) / 2.0,
+                TextAlign::Right => width - line.width,
+            };
+            if line.words.is_empty() {
+                continue;
+            }
+            left = left.min(x);
+            right = right.max(x + line.width);
+
+            // Stretch the spaces to fill the width, when justifying.
+            let gaps = line.words.len() as f32 - 1.0;
+            let stretch = match self.align {
+                TextAlign::Justify if !line.last && gaps > 0.0 => {
+                    right = right.max(width);
+                    (width - line.width).max(0.0) / gaps
+                }
+                _ => 0.0,
+            };
+
+            let first = runs.len();
+            for (i, word) in line.words.iter().enumerate() {
+                if i > 0 {
+                    x += word.space + stretch;
+                }
+                for (j, piece) in word.pieces.iter().enumerate() {
+                    // Join words of the same span (with the space between),
+                    // unless the space is stretched.
+                    let spaced = i > 0 && j == 0 && stretch == 0.0;
+                    match runs[first..].last_mut() {
+                        Some(run)
+                            if spaced
+                                && run.span == piece.span
+                                && word.space_span == piece.span =>
+                        {
+                            run.text.push(' ');
+                            run.text.push_str(piece.text);
+                            run.width = x + piece.width - run.x;
+                        }
+                        _ => runs.push(Run {
+                            text: piece.text.to_string(),
+                            x,
+                            y: baseline - piece.ascent,
+                            width: piece.width,
+                            baseline,
+                            span: piece.span,
+                        }),
+                    }
+                    x += piece.width;
+                }
+            }
+        }
+
+        Paragraph {
+            runs,
+            spans: spans.to_vec(),
+            size: self.size,
+            bbox: (left.min(right), 0.0, (right - left).max(0.0), top),
+        }
+    }
+
+    // Break `spans` into lines of words, measuring each piece of a word.
+    fn wrap<'a>(
+        &self,
+        font: &FontGroup,
+        spans: &[Span<'a>],
+    ) -> Vec<TextLine<'a>> {
+        let max = self.width.unwrap_or(f32::INFINITY);
+        let mut lines = vec![];
+        let mut line = TextLine::default();
+        let mut word = Word::default();
+        // Width and span of the space before the next word.
+        let mut space = None;
+
+        for (i, span) in spans.iter().enumerate() {
+            let font = span.font.unwrap_or(font);
+            let size = span.size.unwrap_or(self.size);
+            for token in tokens(span.text) {
+                match token {
+                    Token::Text(text) => {
+                        let metrics = measure(font, size, text);
+                        if word.pieces.is_empty() {
+                            let (width, span) = space.unwrap_or((0.0, i));
+                            word.space = width;
+                            word.space_span = span;
+                        }
+                        word.width += metrics.advance;
+                        word.pieces.push(Piece {
+                            text,
+                            span: i,
+                            width: metrics.advance,
+                            ascent: metrics.ascent,
+                        });
+                    }
+                    Token::Space => {
+                        line.add(mem::take(&mut word), max, &mut lines);
+                        let width = measure(font, size, " ").advance;
+                        space = Some((width, i));
+                    }
+                    Token::Newline => {
+                        line.add(mem::take(&mut word), max, &mut lines);
+                        line.last = true;
+                        lines.push(mem::take(&mut line));
+                        space = None;
+                    }
+                }
+            }
+        }
+        line.add(word, max, &mut lines);
+        line.last = true;
+        lines.push(line);
+        lines
+    }
+}
+
+/// A piece of rich text with its own style, for
+/// `TextLayout::lay_out_spans()`.  Unless set, spans use the paint, font and
+/// size the paragraph is laid out and drawn with.
+///
+/// ```
+/// use barg::{FontGroup, Image, Size, Span, TextLayout};
+///
+/// let font = FontGroup::default();
+/// let mut image = Image::new(Size(200, 40));
+/// let mut buffer = vec![0; 200 * 40 * 4];
+///
+/// // Highlight a search match.
+/// let spans = [
+///     Span::new("Search "),
+///     Span::new("match").paint([255, 0, 0, 255]).underline(true),
+///     Span::new("ing text"),
+/// ];
+/// let paragraph = TextLayout::new(16.0).lay_out_spans(&font, &spans);
+/// let black = [0, 0, 0, 255];
+/// image
+///     .paragraph(black, (0.0, 0.0), &font, &paragraph, &mut buffer)
+///     .unwrap();
+/// ```
+#[derive(Copy, Clone)]
+pub struct Span<'a> {
+    // The text.
+    text: &'a str,
+    // Paint, if not the paragraph's.
+    paint: Option<Paint<'a>>,
+    // Font size, if not the layout's.
+    size: Option<f32>,
+    // Font (such as a bold one), if not the layout's.
+    font: Option<&'a FontGroup<'a>>,
+    // If there's a line under the text.
+    underline: bool,
+    // If there's a line through the text.
+    strikethrough: bool,
+}
+
+impl<'a> Span<'a> {
+    /// Create a span of `text`, styled like the rest of the paragraph.
+    pub fn new(text: &'a str) -> Self {
+        Span {
+            text,
+            paint: None,
+            size: None,
+            font: None,
+            underline: false,
+            strikethrough: false,
+        }
+    }
+
+    /// Set the color (sRGBA) or other `Paint`.
+    pub fn paint<P: Into<Paint<'a>>>(self, paint: P) -> Self {
+        Span {
+            paint: Some(paint.into()),
+            ..self
+        }
+    }
+
+    /// Set the font size.
+    pub fn size(self, size: f32) -> Self {
+        Span {
+            size: Some(size),
+            ..self
+        }
+    }
+
+    /// Set the font (for example, a bold or italic one).
+    pub fn font(self, font: &'a FontGroup<'a>) -> Self {
+        Span {
+            font: Some(font),
+            ..self
+        }
+    }
+
+    /// Set if there's a line under the text.
+    pub fn underline(self, underline: bool) -> Self {
+        Span { underline, ..self }
+    }
+
+    /// Set if there's a line through the text.
+    pub fn strikethrough(self, strikethrough: bool) -> Self {
+        Span {
+            strikethrough,
+            ..self
+        }
+    }
+}
+
+// A piece of text that's part of the text of a span, split at whitespace.
+enum Token<'a> {
+    Text(&'a str),
+    Space,
+    Newline,
+}
+
+// Split `text` into pieces of text, spaces (each whitespace character) and
+// newlines.
+fn tokens(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if !c.is_whitespace() {
+            continue;
+        }
+        if start < i {
+            tokens.push(Token::Text(&text[start..i]));
+        }
+        tokens.push(if c == '\n' {
+            Token::Newline
+        } else {
+            Token::Space
+        });
+        start = i + c.len_utf8();
+    }
+    if start < text.len() {
+        tokens.push(Token::Text(&text[start..]));
+    }
+    tokens
+}
+
+// The part of a word in one span.
+struct Piece<'a> {
+    text: &'a str,
+    // Which span it's from.
+    span: usize,
+    width: f32,
+    // Distance from the top of the text to the baseline.
+    ascent: f32,
+}
+
+// A word, which may be made of pieces of several spans.
+#[derive(Default)]
+struct Word<'a> {
+    pieces: Vec<Piece<'a>>,
+    width: f32,
+    // Width and span of the space before the word.
+    space: f32,
+    space_span: usize,
+}
+
+// A line of words, before placing them.
+#[derive(Default)]
+struct TextLine<'a> {
+    words: Vec<Word<'a>>,
+    // Width with one space between words.
+    width: f32,
+    // If this is the end of the paragraph or before a newline.
+    last: bool,
+}
+
+impl<'a> TextLine<'a> {
+    // Add a word to the line, or to a new line (moving this one to `lines`)
+    // if it doesn't fit `max`.
+    fn add(&mut self, word: Word<'a>, max: f32, lines: &mut Vec<Self>) {
+        if word.pieces.is_empty() {
+            return;
+        }
+        if self.words.is_empty() {
+            self.width = word.width;
+        } else if self.width + word.space + word.width > max {
+            lines.push(mem::take(self));
+            self.width = word.width;
+        } else {
+            self.width += word.space + word.width;
+        }
+        self.words.push(word);
+    }
+}
+
+/// Text laid out with a `TextLayout`, ready to draw with
+/// `Image::paragraph()`.
+#[derive(Clone)]
+pub struct Paragraph<'a> {
+    // Pieces of text to draw, with where to draw them.
+    runs: Vec<Run>,
+    // Styles of the runs.
+    spans: Vec<Span<'a>>,
+    // Font size.
+    size: f32,
+    // Bounding box (x, y, width, height).
+    bbox: (f32, f32, f32, f32),
+}
+
+impl<'a> Paragraph<'a> {
+    /// Get the bounding box (x, y, width, height) of the lines, from where
+    /// the paragraph is drawn.  The height includes empty lines.
+    pub fn bbox(&self) -> (f32, f32, f32, f32) {
+        self.bbox
+    }
+
+    // Get the pieces of text to draw.
+    pub(crate) fn runs(&self) -> &[Run] {
+        &self.runs
+    }
+
+    // Get the paint, font size and font of a run, given the paragraph's
+    // paint and font.
+    pub(crate) fn style<'s>(
+        &'s self,
+        run: &Run,
+        paint: Paint<'s>,
+        font: &'s FontGroup<'s>,
+    ) -> (Paint<'s>, f32, &'s FontGroup<'s>) {
+        let span = &self.spans[run.span];
+        (
+            span.paint.unwrap_or(paint),
+            span.size.unwrap_or(self.size),
+            span.font.unwrap_or(font),
+        )
+    }
+
+    // Get the lines to draw under and through a run (as rectangles).
+    pub(crate) fn decorations(&self, run: &Run) -> Vec<(f32, f32, f32, f32)> {
+        let span = &self.spans[run.span];
+        let size = span.size.unwrap_or(self.size);
+        let thickness = (size / 16.0).max(1.0);
+        let mut lines = vec![];
+        if span.underline {
+            let y = run.baseline + size / 10.0;
+            lines.push((run.x, y, run.width, thickness));
+        }
+        if span.strikethrough {
+            let y = run.baseline - size / 4.0 - thickness / 2.0;
+            lines.push((run.x, y, run.width, thickness));
+        }
+        lines
+    }
+}
+
+// A piece of a `Paragraph` in one span, drawn at (`x`, `y`) from the
+// paragraph's top left.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Run {
+    pub(crate) text: String,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) width: f32,
+    // Y of the baseline, for underlines and strikethroughs.
+    baseline: f32,
+    // Which span it's from.
+    span: usize,
+}
+
+/// The size of text, from `measure()`.  Distances are in pixels, from where
+/// the text is drawn (its top left, as with `Image::text()`).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextMetrics {
+    /// How far the pen moves to the right (to where the next text would
+    /// start).
+    pub advance: f32,
+    /// Distance from the top of the line to the baseline.
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the line.
+    pub descent: f32,
+    /// Bounding box (x, y, width, height) of the ink of the glyphs, or all 0
+    /// if nothing is drawn (such as for spaces).
+    pub bbox: (f32, f32, f32, f32),
+}
+
+/// Measure `text` drawn with `font` at `size`, without drawing it.  Useful
+/// for placing text before drawing it with `Image::text()`.
+///
+/// ```
+/// use barg::{measure, FontGroup};
+///
+/// let font = FontGroup::default();
+/// let metrics = measure(&font, 16.0, "Hello");
+///
+/// // The line is as tall as the font size.
+/// assert_eq!(metrics.ascent + metrics.descent, 16.0);
+/// let (_, y, _, h) = metrics.bbox;
+/// assert!(y > 0.0 && y + h <= metrics.ascent + 0.5);
+/// ```
+pub fn measure(font: &FontGroup, size: f32, text: &str) -> TextMetrics {
+    // Fonts are scaled so the line (ascent and descent) is `size` tall, and
+    // a newline moves down by the ascent.
+    let mut newline = font.render("\n", (0.0, 0.0), (size, size));
+    for _ in &mut newline {}
+    let ascent = newline.xy().1;
+
+    let mut path = font.render(text, (0.0, 0.0), (size, size));
+    let mut bounds = Bounds::default();
+    for op in &mut path {
+        bounds.add(op);
+    }
+
+    TextMetrics {
+        advance: path.xy().0,
+        ascent,
+        descent: size - ascent,
+        bbox: bounds.bbox(),
+    }
+}
+
+/// Shorten `text` drawn with `font` at `size` to fit `width`, replacing
+/// what's cut with an ellipsis ("…").  Text is only cut between graphemes
+/// (what readers see as characters), so accents stay on their letters.
+/// Returns `text` if it already fits, or nothing if the ellipsis doesn't.
+///
+/// ```
+/// use barg::{truncate, Ellipsis, FontGroup};
+///
+/// // The built-in font is monospace: 8 pixels per letter at size 16.
+/// let font = FontGroup::default();
+/// let text = "Splat And… ‽é¿?üæ";
+/// let end = truncate(&font, 16.0, text, 64.0, Ellipsis::End);
+/// let middle = truncate(&font, 16.0, text, 64.0, Ellipsis::Middle);
+/// assert_eq!(end, "Splat A…");
+/// assert_eq!(middle, "Spla…?üæ");
+/// ```
+pub fn truncate(
+    font: &FontGroup,
+    size: f32,
+    text: &str,
+    width: f32,
+    ellipsis: Ellipsis,
+) -> String {
+    let fits = |text: &str| measure(font, size, text).advance <= width;
+    if fits(text) {
+        return text.to_string();
+    }
+
+    // Keep `n` graphemes.
+    let graphemes: Vec<&str> = text.graphemes(true).collect();
+    let keep = |n: usize| {
+        let (start, end) = match ellipsis {
+            Ellipsis::End => (n, 0),
+            Ellipsis::Middle => (n - n / 2, n / 2),
+        };
+        let start = graphemes[..start].concat();
+        let end = graphemes[graphemes.len() - end..].concat();
+        format!("{}…{}", start.trim_end(), end.trim_start())
+    };
+    if !fits(&keep(0)) {
+        return String::new();
+    }
+
+    // Find the most graphemes that fit (fewer than all, which didn't).
+    let (mut fit, mut too_many) = (0, graphemes.len());
+    while too_many - fit > 1 {
+        let n = (fit + too_many) / 2;
+        if fits(&keep(n)) {
+            fit = n;
+        } else {
+            too_many = n;
+        }
+    }
+    keep(fit)
+}
+
+// Tight bounds of a path, with curves bounded by their extremes (rather than
+// their control points).
+#[derive(Default)]
+pub(crate) struct Bounds {
+    // Minimum and maximum x and y, if there are any points.
+    min_max: Option<(f32, f32, f32, f32)>,
+    // Current point.
+    pen: (f32, f32),
+}
+
+impl Bounds {
+    // Add a path operation.  Glyphs are only made of moves, lines and
+    // quadratic curves.
+    pub(crate) fn add(&mut self, op: &PathOp) {
+        match *op {
+            Move(x, y) | Line(x, y) => self.point(x, y),
+            Quad(cx, cy, x, y) => {
+                let (x0, y0) = self.pen;
+                let tx = extreme(x0, cx, x);
+                let ty = extreme(y0, cy, y);
+                for t in tx.iter().chain(ty.iter()) {
+                    let at = |a: f32, b: f32, c: f32| {
+                        let s = 1.0 - t;
+                        s * s * a + 2.0 * s * t * b + t * t * c
+                    };
+                    self.point(at(x0, cx, x), at(y0, cy, y));
+                }
+                self.point(x, y);
+            }
+            _ => {}
+        }
+    }
+
+    // Add a point, and move the pen to it.
+    fn point(&mut self, x: f32, y: f32) {
+        let (x0, y0, x1, y1) = self.min_max.unwrap_or((x, y, x, y));
+        self.min_max = Some((x0.min(x), y0.min(y), x1.max(x), y1.max(y)));
+        self.pen = (x, y);
+    }
+
+    // Get the bounding box (x, y, width, height).
+    pub(crate) fn bbox(&self) -> (f32, f32, f32, f32) {
+        match self.min_max {
+            Some((x0, y0, x1, y1)) => (x0, y0, x1 - x0, y1 - y0),
+            None => (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+// Get where (0 to 1) a quadratic curve from `a` to `c` (with control point
+// `b`) turns around on one axis, if it does.
+fn extreme(a: f32, b: f32, c: f32) -> Option<f32> {
+    let d = a - 2.0 * b + c;
+    let t = (a - b) / d;
+    if d != 0.0 && t > 0.0 && t < 1.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
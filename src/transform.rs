@@ -0,0 +1,69 @@
+//! Affine matrices [a, b, c, d, e, f], which map (x, y) to
+//! (a·x + c·y + e, b·x + d·y + f).
+
+use fonterator::PathOp::{self, *};
+
+// The matrix that leaves points where they are.
+pub(crate) const IDENTITY: [f32; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+// Invert an affine `matrix`.
+pub(crate) fn invert(matrix: [f32; 6]) -> Option<[f32; 6]> {
+    let [a, b, c, d, e, f] = matrix;
+    let det = a * d - b * c;
+    if det.abs() <= f32::EPSILON {
+        return None;
+    }
+    let (a, b, c, d) = (d / det, -b / det, -c / det, a / det);
+
+    Some([a, b, c, d, -(a * e + c * f), -(b * e + d * f)])
+}
+
+// Multiply affine matrices, for a matrix that applies `b` and then `a`.
+pub(crate) fn multiply(a: [f32; 6], b: [f32; 6]) -> [f32; 6] {
+    [
+        a[0] * b[0] + a[2] * b[1],
+        a[1] * b[0] + a[3] * b[1],
+        a[0] * b[2] + a[2] * b[3],
+        a[1] * b[2] + a[3] * b[3],
+        a[0] * b[4] + a[2] * b[5] + a[4],
+        a[1] * b[4] + a[3] * b[5] + a[5],
+    ]
+}
+
+// Transform a point with `matrix`.
+pub(crate) fn point(matrix: [f32; 6], x: f32, y: f32) -> (f32, f32) {
+    let m = matrix;
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+// Transform a path operation with `matrix`.  Pen widths are scaled by how
+// much the matrix scales areas (the square root of it).
+pub(crate) fn path_op(matrix: [f32; 6], op: &PathOp) -> PathOp {
+    let p = |x, y| point(matrix, x, y);
+
+    match *op {
+        Move(x, y) => {
+            let (x, y) = p(x, y);
+            Move(x, y)
+        }
+        Line(x, y) => {
+            let (x, y) = p(x, y);
+            Line(x, y)
+        }
+        Quad(bx, by, x, y) => {
+            let ((bx, by), (x, y)) = (p(bx, by), p(x, y));
+            Quad(bx, by, x, y)
+        }
+        Cubic(bx, by, cx, cy, x, y) => {
+            let ((bx, by), (cx, cy), (x, y)) = (p(bx, by), p(cx, cy), p(x, y));
+            Cubic(bx, by, cx, cy, x, y)
+        }
+        Close() => Close(),
+        PenWidth(w) => PenWidth(w * scale(matrix)),
+    }
+}
+
+// Get how much `matrix` scales lengths, on average.
+pub(crate) fn scale(matrix: [f32; 6]) -> f32 {
+    (matrix[0] * matrix[3] - matrix[1] * matrix[2]).abs().sqrt()
+}